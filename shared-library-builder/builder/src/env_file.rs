@@ -0,0 +1,87 @@
+use std::error::Error;
+use std::path::Path;
+
+/// Read `KEY=VALUE` lines and set them in the environment of the builder, so that
+/// everything it spawns (Configure, make, the NDK toolchain, signing tools) sees them.
+/// Blank lines and `#` comments are skipped, an `export ` prefix and surrounding quotes
+/// are removed, and `${NAME}` is replaced with the current value of `NAME`,
+/// for example `PATH=/opt/cross/bin:${PATH}`
+pub fn load_env_file(file: &Path) -> Result<(), Box<dyn Error>> {
+    let contents = std::fs::read_to_string(file)
+        .map_err(|error| format!("Could not read {}: {}", file.display(), error))?;
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line.split_once('=').ok_or_else(|| {
+            format!(
+                "{}:{}: expected KEY=VALUE, got {}",
+                file.display(),
+                index + 1,
+                line
+            )
+        })?;
+        let key = key.trim();
+        let value = value.trim();
+        let value = if value.len() >= 2
+            && ((value.starts_with('"') && value.ends_with('"'))
+                || (value.starts_with('\'') && value.ends_with('\'')))
+        {
+            value[1..value.len() - 1].to_string()
+        } else {
+            value.to_string()
+        };
+        std::env::set_var(key, expand(&value));
+    }
+    Ok(())
+}
+
+/// Replace `${NAME}` with the value of the environment variable, or nothing if it is not set
+fn expand(value: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&std::env::var(&rest[start + 2..end]).unwrap_or_default());
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_set_variables() {
+        std::env::set_var("ENV_FILE_TEST_CROSS", "/opt/cross/bin");
+        assert_eq!(
+            expand("${ENV_FILE_TEST_CROSS}:/usr/bin"),
+            "/opt/cross/bin:/usr/bin"
+        );
+        assert_eq!(
+            expand("a${ENV_FILE_TEST_CROSS}b${ENV_FILE_TEST_CROSS}"),
+            "a/opt/cross/binb/opt/cross/bin"
+        );
+    }
+
+    #[test]
+    fn unset_variables_expand_to_nothing() {
+        std::env::remove_var("ENV_FILE_TEST_UNSET");
+        assert_eq!(expand("-L${ENV_FILE_TEST_UNSET}/lib"), "-L/lib");
+    }
+
+    #[test]
+    fn unterminated_references_are_kept() {
+        assert_eq!(expand("plain value"), "plain value");
+        assert_eq!(expand("${UNTERMINATED"), "${UNTERMINATED");
+        assert_eq!(expand("$HOME"), "$HOME");
+    }
+}
@@ -1,26 +1,317 @@
+mod env_file;
+
 use std::error::Error;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use shared_library_builder::{with_target, Library, LibraryCompilationContext, LibraryTarget};
 
-use shared_library_builder::{with_target, Library, LibraryCompilationContext};
+use libopenssl_library::{
+    libcrypto, libssl, supported_targets, verify_existing_libraries, GitHubRelease, LogLevel,
+    OpenSSLLibrary, SourceArchive,
+};
+use serde_json::{json, Value};
 
-use libopenssl_library::{libcrypto, libssl};
+use crate::env_file::load_env_file;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    if std::env::args().nth(1).as_deref() == Some("list-targets") {
+        list_targets();
+        return Ok(());
+    }
+    // loaded first, so that the variables apply to everything below
+    if let Some(env_file) = option_value("--env-file") {
+        load_env_file(Path::new(&env_file))?;
+    }
+    if std::env::args().nth(1).as_deref() == Some("verify") {
+        return verify();
+    }
+    let dry_run = std::env::args().any(|arg| arg == "--dry-run");
+    let clean = std::env::args().nth(1).as_deref() == Some("clean");
+    let package = std::env::args().nth(1).as_deref() == Some("package");
+    let clean_sources = std::env::args().any(|arg| arg == "--sources");
+    // with `--format json` stdout only receives the json, progress goes to stderr
+    let json = option_value("--format").as_deref() == Some("json");
+
     let src_path = Path::new("target/src");
     if !src_path.exists() {
         std::fs::create_dir_all(&src_path)?;
     }
 
+    if std::env::args().nth(1).as_deref() == Some("xcframework") {
+        return xcframework(src_path);
+    }
+    if std::env::args().nth(1).as_deref() == Some("jnilibs") {
+        return jni_libs(src_path);
+    }
+
+    // a vendored source archive lets air-gapped machines build without git or network access
+    let vendored_archive = std::env::var_os("OPENSSL_VENDORED_ARCHIVE")
+        .map(SourceArchive::vendored)
+        .or_else(|| SourceArchive::detect_vendored("vendor"));
+
+    let options = BuildOptions {
+        sources: src_path.to_path_buf(),
+        vendored_archive,
+        dry_run,
+        clean,
+        clean_sources,
+        package,
+        package_output: option_value("--output").map(PathBuf::from),
+        json,
+    };
+
+    // several targets reuse the fetched sources, each builds in its own directory
+    if let Some(targets) = option_value("--targets") {
+        let mut outputs = vec![];
+        for target in parse_targets(&targets)? {
+            outputs.extend(build(target, &options)?);
+        }
+        if json {
+            println!("{}", serde_json::to_string_pretty(&outputs)?);
+        }
+        return Ok(());
+    }
+
     with_target(|target| {
-        let version: Option<String> = None;
-        let crypto = libcrypto(version.clone());
-        let ssl = libssl(version.clone());
+        if let Some(output) = build(target, &options)? {
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        Ok(())
+    })
+}
+
+struct BuildOptions {
+    sources: PathBuf,
+    vendored_archive: Option<SourceArchive>,
+    dry_run: bool,
+    clean: bool,
+    clean_sources: bool,
+    package: bool,
+    package_output: Option<PathBuf>,
+    json: bool,
+}
+
+/// Build libcrypto and libssl for the target. Returns the description of the build
+/// in json mode
+fn build(target: LibraryTarget, options: &BuildOptions) -> Result<Option<Value>, Box<dyn Error>> {
+    let version: Option<String> = None;
+    let mut crypto = libcrypto(version.clone());
+    let mut ssl = libssl(version.clone());
+
+    if options.json {
+        crypto = crypto.with_log_level(LogLevel::Silent);
+        ssl = ssl.with_log_level(LogLevel::Silent);
+    }
+
+    if let Some(vendored_archive) = &options.vendored_archive {
+        // the version selects Configure options, so it must match the archive
+        if vendored_archive.openssl_version().is_none() {
+            return Err(format!(
+                "Can not tell the OpenSSL version of {}, name it like openssl-3.0.13.tar.gz",
+                vendored_archive.url()
+            )
+            .into());
+        }
+        eprintln!("Using vendored sources {}", vendored_archive.url());
+        crypto = crypto.with_source_archive(vendored_archive.clone());
+        ssl = ssl.with_source_archive(vendored_archive.clone());
+    }
 
-        let context = LibraryCompilationContext::new(src_path, "target", target, false);
-        let compiled_crypto = crypto.compile(&context)?;
+    let context =
+        LibraryCompilationContext::new(options.sources.as_path(), "target", target, false);
+    if options.clean {
+        // libcrypto and libssl share the build directory and the sources
+        crypto.clean(&context, options.clean_sources)?;
+        return Ok(None);
+    }
+    if options.dry_run {
+        // libcrypto and libssl share one build
+        print!("{}", crypto.clone().be_both().plan(&context)?);
+        return Ok(None);
+    }
+    if options.package {
+        return package(&crypto, &context, options);
+    }
+
+    let (compiled_crypto, report) = crypto.compile_with_report(&context)?;
+    let compiled_ssl = ssl.compile(&context)?;
+    if !options.json {
         println!("Compiled {}", compiled_crypto.display());
-        let compiled_ssl = ssl.compile(&context)?;
+        report.print_summary();
         println!("Compiled {}", compiled_ssl.display());
-        Ok(())
-    })
+    }
+
+    // the release pipeline uploads one archive with both libraries per target
+    let mut published = vec![];
+    if let Ok(tag) = std::env::var("OPENSSL_PUBLISH_TAG") {
+        let release = GitHubRelease::new("feenkcom", "libopenssl", tag);
+        published = crypto.clone().be_both().publish(&context, &release)?;
+        if !options.json {
+            for url in &published {
+                println!("Published {}", url);
+            }
+        }
+    }
+
+    if !options.json {
+        return Ok(None);
+    }
+    Ok(Some(json!({
+        "target": context.target().to_string(),
+        "libraries": {
+            "crypto": compiled_crypto,
+            "ssl": compiled_ssl,
+        },
+        "include_directories": crypto.native_library_include_headers(&context),
+        "pkg_config_directory": crypto.pkg_config_directory(&context),
+        "report": report,
+        "published": published,
+    })))
+}
+
+/// `package [--output <directory>]` bundles the libraries compiled before into the archive,
+/// checksum and manifest that are uploaded to a release, and copies them to the output directory
+fn package(
+    crypto: &OpenSSLLibrary,
+    context: &LibraryCompilationContext,
+    options: &BuildOptions,
+) -> Result<Option<Value>, Box<dyn Error>> {
+    // the release archive contains both libraries
+    let mut assets = crypto.clone().be_both().release_assets(context)?;
+    if let Some(output) = &options.package_output {
+        std::fs::create_dir_all(output)?;
+        for asset in assets.iter_mut() {
+            let file_name = asset
+                .file_name()
+                .ok_or_else(|| format!("{} has no file name", asset.display()))?;
+            let copy = output.join(file_name);
+            std::fs::copy(asset.as_path(), &copy)?;
+            *asset = copy;
+        }
+    }
+    if options.json {
+        return Ok(Some(json!({
+            "target": context.target().to_string(),
+            "assets": assets,
+        })));
+    }
+    for asset in &assets {
+        println!("Packaged {}", asset.display());
+    }
+    Ok(None)
+}
+
+/// `xcframework --targets <triple>,...` builds the Apple targets and assembles
+/// libcrypto.xcframework and libssl.xcframework
+fn xcframework(sources: &Path) -> Result<(), Box<dyn Error>> {
+    let targets = option_value("--targets")
+        .ok_or("Usage: builder xcframework --targets aarch64-apple-darwin,x86_64-apple-darwin")?;
+    let contexts = parse_targets(&targets)?
+        .into_iter()
+        .map(|target| LibraryCompilationContext::new(sources, "target", target, false))
+        .collect::<Vec<LibraryCompilationContext>>();
+    for xcframework in libcrypto(None::<String>).compile_xcframeworks(&contexts)? {
+        println!("Assembled {}", xcframework.display());
+    }
+    Ok(())
+}
+
+/// `jnilibs --targets <triple>,... [--output <directory>]` builds the Android targets and
+/// arranges the libraries as `<directory>/<abi>/`, `target/jniLibs` by default
+fn jni_libs(sources: &Path) -> Result<(), Box<dyn Error>> {
+    let targets = option_value("--targets")
+        .ok_or("Usage: builder jnilibs --targets aarch64-linux-android [--output <directory>]")?;
+    let output = option_value("--output")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("target/jniLibs"));
+    let contexts = parse_targets(&targets)?
+        .into_iter()
+        .map(|target| LibraryCompilationContext::new(sources, "target", target, false))
+        .collect::<Vec<LibraryCompilationContext>>();
+    for library in libcrypto(None::<String>).compile_jni_libs(&contexts, &output)? {
+        println!("Copied {}", library.display());
+    }
+    Ok(())
+}
+
+/// Parse a comma separated list of targets
+fn parse_targets(targets: &str) -> Result<Vec<LibraryTarget>, Box<dyn Error>> {
+    targets
+        .split(',')
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+        .map(|target| {
+            target
+                .parse::<LibraryTarget>()
+                .map_err(|_| format!("Unknown target {}", target).into())
+        })
+        .collect()
+}
+
+/// `verify <libcrypto> <libssl> [--target <triple>] [--expect-version <major.minor>]` checks
+/// libraries that were built before without rebuilding them
+fn verify() -> Result<(), Box<dyn Error>> {
+    let mut libraries = std::env::args().skip(2).take(2);
+    let usage = "Usage: builder verify <libcrypto> <libssl> [--target <triple>] [--expect-version <major.minor>]";
+    let crypto = PathBuf::from(libraries.next().ok_or(usage)?);
+    let ssl = PathBuf::from(libraries.next().ok_or(usage)?);
+    let host = LibraryTarget::for_current_host().to_string();
+    let target = option_value("--target").unwrap_or_else(|| host.clone());
+    let expected = match option_value("--expect-version") {
+        Some(version) => {
+            let (major, minor) = version
+                .split_once('.')
+                .ok_or_else(|| format!("Expected <major.minor>, got {}", version))?;
+            Some((major.parse::<u32>()?, minor.parse::<u32>()?))
+        }
+        None => None,
+    };
+
+    match verify_existing_libraries(&crypto, &ssl, &target, &host, expected)? {
+        Some(version_num) => println!(
+            "Verified {} and {} (version {:#x})",
+            crypto.display(),
+            ssl.display(),
+            version_num
+        ),
+        None => println!(
+            "Verified the exported symbols of {} and {}, {} libraries can not be loaded on {}",
+            crypto.display(),
+            ssl.display(),
+            target,
+            host
+        ),
+    }
+    Ok(())
+}
+
+/// The value of `--name value` or `--name=value`
+fn option_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&format!("{}=", name)) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Print the Rust targets the builder supports with their OpenSSL Configure targets
+fn list_targets() {
+    // only the triples that --targets accepts
+    let targets = supported_targets()
+        .into_iter()
+        .filter(|(triple, _)| triple.parse::<LibraryTarget>().is_ok())
+        .collect::<Vec<(&str, &str)>>();
+    let width = targets
+        .iter()
+        .map(|(triple, _)| triple.len())
+        .max()
+        .unwrap_or_default();
+    for (triple, configure_target) in targets {
+        println!("{:width$}  {}", triple, configure_target, width = width);
+    }
 }
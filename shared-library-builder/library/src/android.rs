@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+
+use crate::OpenSSLBuildError;
+
+/// Environment variables that point to the NDK, in the order they are looked up
+pub(crate) const NDK_VARIABLES: &[&str] = &[
+    "ANDROID_NDK",
+    "NDK_HOME",
+    "ANDROID_NDK_ROOT",
+    "ANDROID_NDK_HOME",
+];
+
+/// An Android NDK with an LLVM toolchain for the host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AndroidNdk {
+    root: PathBuf,
+}
+
+impl AndroidNdk {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, OpenSSLBuildError> {
+        let ndk = Self { root: root.into() };
+        if !ndk.toolchain_bin().is_dir() {
+            return Err(OpenSSLBuildError::EnvironmentMissing(format!(
+                "{} is not an Android NDK, it has no {}",
+                ndk.root.display(),
+                ndk.toolchain_bin().display()
+            )));
+        }
+        Ok(ndk)
+    }
+
+    /// The NDK given by `ANDROID_NDK`, `NDK_HOME`, `ANDROID_NDK_ROOT` or `ANDROID_NDK_HOME`,
+    /// or the one found in the Android SDK
+    pub fn from_env() -> Result<Self, OpenSSLBuildError> {
+        if let Some(root) = NDK_VARIABLES
+            .iter()
+            .find_map(|variable| std::env::var_os(variable).filter(|root| !root.is_empty()))
+        {
+            return Self::new(root);
+        }
+        let ndk = ndk_build::ndk::Ndk::from_env().map_err(|error| {
+            OpenSSLBuildError::EnvironmentMissing(format!(
+                "Could not find the Android NDK, set ANDROID_NDK or use with_ndk: {}",
+                error
+            ))
+        })?;
+        Self::new(ndk.ndk())
+    }
+
+    pub fn root(&self) -> &Path {
+        self.root.as_path()
+    }
+
+    /// The directory with the clang wrappers of every ABI and API level
+    pub fn toolchain_bin(&self) -> PathBuf {
+        let host = if cfg!(target_os = "windows") {
+            "windows-x86_64"
+        } else if cfg!(target_os = "macos") {
+            // also used on arm64 macs
+            "darwin-x86_64"
+        } else {
+            "linux-x86_64"
+        };
+        self.root
+            .join("toolchains")
+            .join("llvm")
+            .join("prebuilt")
+            .join(host)
+            .join("bin")
+    }
+
+    /// Check that the NDK has a clang for the target and API level, for example
+    /// `aarch64-linux-android21-clang`
+    pub fn check_target(&self, target: &str, api: &str) -> Result<(), OpenSSLBuildError> {
+        let clang = self.toolchain_bin().join(format!(
+            "{}{}-clang{}",
+            target,
+            api,
+            if cfg!(target_os = "windows") {
+                ".cmd"
+            } else {
+                ""
+            }
+        ));
+        if !clang.exists() {
+            return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                "The Android NDK at {} does not support {} with API level {}, {} does not exist",
+                self.root.display(),
+                target,
+                api,
+                clang.display()
+            )));
+        }
+        Ok(())
+    }
+}
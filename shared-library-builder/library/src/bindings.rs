@@ -0,0 +1,51 @@
+#[cfg(feature = "bindgen")]
+use std::error::Error;
+#[cfg(feature = "bindgen")]
+use std::path::{Path, PathBuf};
+
+/// Written into the install prefix
+pub const BINDINGS_FILE: &str = "bindings.rs";
+
+/// The public headers of the API, covered by the bindings and the module maps of xcframeworks
+pub(crate) const PUBLIC_HEADERS: &[&str] = &[
+    "openssl/crypto.h",
+    "openssl/err.h",
+    "openssl/evp.h",
+    "openssl/rand.h",
+    "openssl/bio.h",
+    "openssl/pem.h",
+    "openssl/x509.h",
+    "openssl/x509v3.h",
+    "openssl/hmac.h",
+    "openssl/ssl.h",
+];
+
+/// Run bindgen over the installed headers, which include the `opensslconf.h` generated
+/// for the target, and write the Rust FFI declarations of the OpenSSL API to the output file
+#[cfg(feature = "bindgen")]
+pub fn generate_bindings(
+    include: &Path,
+    target: &str,
+    output: &Path,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let wrapper = PUBLIC_HEADERS
+        .iter()
+        .map(|header| format!("#include <{}>\n", header))
+        .collect::<String>();
+
+    let bindings = bindgen::Builder::default()
+        .header_contents("openssl-bindings.h", &wrapper)
+        .clang_arg(format!("-I{}", include.display()))
+        .clang_arg(format!("--target={}", target))
+        .allowlist_function("(OPENSSL|CRYPTO|ERR|EVP|RAND|BIO|PEM|X509|HMAC|SSL|TLS|DTLS)_.*")
+        .allowlist_type("(OPENSSL|CRYPTO|ERR|EVP|RAND|BIO|PEM|X509|HMAC|SSL|TLS|DTLS).*")
+        .allowlist_var("(OPENSSL|CRYPTO|ERR|EVP|RAND|BIO|PEM|X509|HMAC|SSL|TLS|DTLS)_.*")
+        .layout_tests(false)
+        .generate()?;
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    bindings.write_to_file(output)?;
+    Ok(output.to_path_buf())
+}
@@ -0,0 +1,106 @@
+use shared_library_builder::{Library, LibraryCompilationContext, LibraryTarget};
+use std::error::Error;
+use std::path::PathBuf;
+
+use crate::android::NDK_VARIABLES;
+use crate::targets::{is_msvc, static_system_libraries};
+use crate::{BuildTool, OpenSSLLibrary};
+
+/// Environment variables that change what the build produces, besides the `OPENSSL_<tool>`
+/// overrides of [`BuildTool`] and the NDK locations
+const BUILD_ENVIRONMENT: &[&str] = &[
+    "CFLAGS",
+    "CXXFLAGS",
+    "CPPFLAGS",
+    "LDFLAGS",
+    "LDLIBS",
+    "CROSS_COMPILE",
+    "ANDROID_HOME",
+    "ANDROID_SDK_ROOT",
+    "OPENSSL_PERL",
+    "OPENSSL_MAKE",
+    "OPENSSL_SYSROOT",
+    "OPENSSL_GIT_MIRROR",
+    "OPENSSL_RELEASE_TOKEN",
+    "OPENSSL_ALLOW_UNVERIFIED_PREBUILT",
+    "OPENSSL_BUILD_CACHE",
+    "SOURCE_DATE_EPOCH",
+    "VCINSTALLDIR",
+];
+
+/// Compile libcrypto and libssl from the `build.rs` of a downstream crate, or install a prebuilt
+/// archive when the library is configured with one, and tell cargo to link against them.
+/// The build happens in `OUT_DIR` for the `TARGET` and `PROFILE` cargo passes to build scripts.
+///
+/// Emits `cargo:rustc-link-search`, `cargo:rustc-link-lib` for libssl and libcrypto
+/// (and the system libraries of a static build), `cargo:rustc-env=OPENSSL_DIR` and
+/// `cargo:root`/`cargo:include`, which a crate with `links = "openssl"` exposes to its dependents
+/// as `DEP_OPENSSL_ROOT` and `DEP_OPENSSL_INCLUDE`. Cargo reruns the build script when one of the
+/// environment variables the build reads, the local sources or a patch file changes.
+/// Returns the install prefix
+pub fn build_and_link(library: OpenSSLLibrary) -> Result<PathBuf, Box<dyn Error>> {
+    let variables = BUILD_ENVIRONMENT
+        .iter()
+        .map(|variable| variable.to_string())
+        .chain(NDK_VARIABLES.iter().map(|variable| variable.to_string()))
+        .chain(
+            BuildTool::ALL
+                .iter()
+                .flat_map(|tool| vec![tool.variable().to_string(), tool.env_variable()]),
+        );
+    for variable in variables {
+        println!("cargo:rerun-if-env-changed={}", variable);
+    }
+    for file in library.input_files() {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+
+    let out_dir = PathBuf::from(
+        std::env::var_os("OUT_DIR").ok_or("OUT_DIR is not set, call from a build script")?,
+    );
+    let target = std::env::var("TARGET")?;
+    let library_target = target
+        .parse::<LibraryTarget>()
+        .map_err(|_| format!("Unsupported target {}", target))?;
+    let is_debug = std::env::var("PROFILE")
+        .map(|profile| profile == "debug")
+        .unwrap_or(false);
+
+    let context = LibraryCompilationContext::new(
+        out_dir.join("src"),
+        out_dir.join("openssl"),
+        library_target,
+        is_debug,
+    );
+    let library = library.be_both();
+    library.compile(&context)?;
+
+    for directory in library.native_library_linker_libraries(&context) {
+        println!("cargo:rustc-link-search=native={}", directory.display());
+    }
+    let kind = if library.is_static() {
+        "static"
+    } else {
+        "dylib"
+    };
+    // OpenSSL names its MSVC import and static libraries libssl.lib and libcrypto.lib
+    let (ssl, crypto) = if is_msvc(&target) {
+        ("libssl", "libcrypto")
+    } else {
+        ("ssl", "crypto")
+    };
+    // libssl depends on libcrypto, so it comes first for linkers that resolve in order
+    println!("cargo:rustc-link-lib={}={}", kind, ssl);
+    println!("cargo:rustc-link-lib={}={}", kind, crypto);
+    if library.is_static() {
+        for system_library in static_system_libraries(&target) {
+            println!("cargo:rustc-link-lib=dylib={}", system_library);
+        }
+    }
+
+    let prefix = library.native_library_prefix(&context);
+    println!("cargo:rustc-env=OPENSSL_DIR={}", prefix.display());
+    println!("cargo:root={}", prefix.display());
+    println!("cargo:include={}", prefix.join("include").display());
+    Ok(prefix)
+}
@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 2 GiB, enough for a few OpenSSL versions and targets
+const DEFAULT_MAX_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// A user-level cache of downloaded source tarballs and prebuilt archives, shared by all
+/// workspaces. Files are stored by their SHA-256, so only downloads with a known checksum
+/// are cached. When the cache grows over its maximum size the least recently used files
+/// are removed.
+///
+/// Configured with `OPENSSL_BUILD_CACHE` (a directory, or `off` to disable the cache)
+/// and `OPENSSL_BUILD_CACHE_SIZE` (the maximum size in MiB)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadCache {
+    directory: PathBuf,
+    max_size: u64,
+}
+
+impl DownloadCache {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// The cache configured by the environment, in the platform cache directory by default
+    pub fn from_env() -> Option<Self> {
+        let directory = match std::env::var_os("OPENSSL_BUILD_CACHE") {
+            Some(value) if value == "off" || value == "0" => return None,
+            Some(value) if !value.is_empty() => PathBuf::from(value),
+            _ => user_cache_directory()?.join("libopenssl"),
+        };
+        let mut cache = Self::new(directory);
+        if let Some(max_size) = std::env::var("OPENSSL_BUILD_CACHE_SIZE")
+            .ok()
+            .and_then(|size| size.trim().parse::<u64>().ok())
+        {
+            cache = cache.with_max_size(max_size * 1024 * 1024);
+        }
+        Some(cache)
+    }
+
+    pub fn directory(&self) -> &Path {
+        self.directory.as_path()
+    }
+
+    fn entry(&self, sha256: &str) -> PathBuf {
+        self.directory.join(sha256.to_lowercase())
+    }
+
+    /// Copy the cached file with the given SHA-256 to the destination
+    pub fn restore(&self, sha256: &str, destination: &Path) -> bool {
+        let entry = self.entry(sha256);
+        if !entry.is_file() {
+            return false;
+        }
+        touch(&entry);
+        if let Some(parent) = destination.parent() {
+            if std::fs::create_dir_all(parent).is_err() {
+                return false;
+            }
+        }
+        std::fs::copy(&entry, destination).is_ok()
+    }
+
+    /// Add a verified download to the cache. Failing to cache is not an error for the build
+    pub fn store(&self, sha256: &str, file: &Path) {
+        let entry = self.entry(sha256);
+        if entry.is_file() {
+            touch(&entry);
+            return;
+        }
+        if std::fs::create_dir_all(&self.directory).is_err() {
+            return;
+        }
+        // copy under a temporary name so that other builds never see a partial file
+        let partial = self
+            .directory
+            .join(format!("{}.{}.part", sha256, std::process::id()));
+        if std::fs::copy(file, &partial).is_err() || std::fs::rename(&partial, &entry).is_err() {
+            let _ = std::fs::remove_file(&partial);
+            return;
+        }
+        self.evict();
+    }
+
+    /// Remove the least recently used files until the cache fits into its maximum size
+    pub fn evict(&self) {
+        let mut entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let metadata = entry.metadata().ok()?;
+                    if !metadata.is_file() {
+                        return None;
+                    }
+                    let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    Some((entry.path(), metadata.len(), used))
+                })
+                .collect::<Vec<(PathBuf, u64, SystemTime)>>(),
+            Err(_) => return,
+        };
+        let mut size: u64 = entries.iter().map(|(_, length, _)| length).sum();
+        entries.sort_by_key(|(_, _, used)| *used);
+        for (path, length, _) in entries {
+            if size <= self.max_size {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                size -= length;
+            }
+        }
+    }
+}
+
+/// Mark a cache entry as recently used
+fn touch(path: &Path) {
+    if let Ok(file) = std::fs::File::options().append(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+fn user_cache_directory() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        return std::env::var_os("LOCALAPPDATA").map(PathBuf::from);
+    }
+    if let Some(xdg_cache_home) = std::env::var_os("XDG_CACHE_HOME").filter(|path| !path.is_empty())
+    {
+        return Some(PathBuf::from(xdg_cache_home));
+    }
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    if cfg!(target_os = "macos") {
+        return Some(home.join("Library").join("Caches"));
+    }
+    Some(home.join(".cache"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn cache(name: &str, max_size: u64) -> DownloadCache {
+        let directory =
+            std::env::temp_dir().join(format!("libopenssl-cache-{}-{}", name, std::process::id()));
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+        std::fs::create_dir_all(&directory).unwrap();
+        DownloadCache::new(directory).with_max_size(max_size)
+    }
+
+    /// A cache entry of ten bytes, last used `age` seconds ago
+    fn entry(cache: &DownloadCache, sha256: &str, age: u64) -> PathBuf {
+        let entry = cache.entry(sha256);
+        std::fs::write(&entry, "0123456789").unwrap();
+        std::fs::File::options()
+            .append(true)
+            .open(&entry)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age))
+            .unwrap();
+        entry
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let cache = cache("evict", 20);
+        let oldest = entry(&cache, "aa", 300);
+        let older = entry(&cache, "bb", 200);
+        let newest = entry(&cache, "cc", 100);
+        cache.evict();
+        assert!(!oldest.exists());
+        assert!(older.exists());
+        assert!(newest.exists());
+        std::fs::remove_dir_all(cache.directory()).unwrap();
+    }
+
+    #[test]
+    fn restored_entries_are_used_recently() {
+        let cache = cache("restore", 20);
+        let restored = entry(&cache, "aa", 300);
+        let older = entry(&cache, "bb", 200);
+        let newest = entry(&cache, "cc", 100);
+        let destination = cache.directory().join("restored").join("archive.tar.gz");
+        assert!(cache.restore("AA", &destination));
+        assert_eq!(std::fs::read_to_string(&destination).unwrap(), "0123456789");
+        std::fs::remove_dir_all(destination.parent().unwrap()).unwrap();
+        cache.evict();
+        assert!(restored.exists());
+        assert!(!older.exists());
+        assert!(newest.exists());
+        std::fs::remove_dir_all(cache.directory()).unwrap();
+    }
+}
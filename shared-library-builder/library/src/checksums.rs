@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::source::sha256_file;
+
+/// Written next to the build outputs, in the format of `sha256sum`
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+/// Write the SHA-256 of every file into `<root>/SHA256SUMS`, with paths relative to the root.
+/// The files are listed in the given order
+pub fn write_checksums(root: &Path, files: &[PathBuf]) -> Result<PathBuf, Box<dyn Error>> {
+    let mut lines = vec![];
+    for file in files {
+        let relative = file.strip_prefix(root).unwrap_or(file);
+        lines.push(format!(
+            "{}  {}",
+            sha256_file(file)?,
+            relative.to_string_lossy().replace('\\', "/")
+        ));
+    }
+
+    let checksums = root.join(CHECKSUMS_FILE);
+    std::fs::write(&checksums, lines.join("\n") + "\n")?;
+    Ok(checksums)
+}
+
+/// Check every file listed in `<root>/SHA256SUMS`, for example of a downloaded release
+pub fn verify_checksums(root: &Path) -> Result<(), Box<dyn Error>> {
+    let checksums = std::fs::read_to_string(root.join(CHECKSUMS_FILE))?;
+    for line in checksums.lines().filter(|line| !line.trim().is_empty()) {
+        let (expected, file) = line
+            .split_once("  ")
+            .ok_or_else(|| format!("Malformed line in {}: {}", CHECKSUMS_FILE, line))?;
+        let actual = sha256_file(&root.join(file))?;
+        if actual != expected {
+            return Err(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                file, expected, actual
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// The regular files directly inside the directories, skipping symlinks
+pub fn binaries_in(directories: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = directories
+        .iter()
+        .filter_map(|directory| std::fs::read_dir(directory).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.symlink_metadata()
+                .map(|metadata| metadata.file_type().is_file())
+                .unwrap_or(false)
+        })
+        .filter(|path| {
+            path.file_name()
+                .map(|name| name != CHECKSUMS_FILE)
+                .unwrap_or(false)
+        })
+        .collect::<Vec<PathBuf>>();
+    files.sort();
+    files.dedup();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "libopenssl-checksums-{}-{}",
+            name,
+            std::process::id()
+        ));
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+        std::fs::create_dir_all(directory.join("lib")).unwrap();
+        directory
+    }
+
+    #[test]
+    fn written_checksums_verify() {
+        let root = directory("verify");
+        let library = root.join("lib").join("libcrypto.so");
+        std::fs::write(&library, "libcrypto").unwrap();
+        let checksums = write_checksums(&root, &[library.clone()]).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&checksums).unwrap(),
+            format!("{}  lib/libcrypto.so\n", sha256_file(&library).unwrap())
+        );
+        assert!(verify_checksums(&root).is_ok());
+
+        std::fs::write(&library, "tampered").unwrap();
+        assert!(verify_checksums(&root).is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn malformed_lines_are_refused() {
+        let root = directory("malformed");
+        std::fs::write(root.join(CHECKSUMS_FILE), "0123 lib/libcrypto.so\n").unwrap();
+        let error = verify_checksums(&root).unwrap_err().to_string();
+        assert!(error.starts_with("Malformed line"), "{}", error);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
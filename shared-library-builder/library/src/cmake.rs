@@ -0,0 +1,104 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// An imported library of the CMake package, such as `OpenSSL::Crypto`
+#[derive(Debug, Clone)]
+pub struct CMakeTarget {
+    pub name: String,
+    /// The shared or static library, a DLL on Windows
+    pub location: PathBuf,
+    /// The import library of a Windows DLL
+    pub import_library: Option<PathBuf>,
+    /// Other targets or system libraries the library links against
+    pub link_libraries: Vec<String>,
+}
+
+/// Write `OpenSSLConfig.cmake`, `OpenSSLConfigVersion.cmake` and `OpenSSLTargets.cmake` into
+/// `lib/cmake/OpenSSL` of the install prefix, so that `find_package(OpenSSL CONFIG)` finds the
+/// `OpenSSL::Crypto` and `OpenSSL::SSL` targets and the variables `FindOpenSSL` defines.
+/// Paths are relative to the files, so the install prefix can be moved.
+/// The version is the full version, such as `3.0.13` or `1.1.1w`, as `FindOpenSSL` reports it
+pub fn write_cmake_package(
+    prefix: &Path,
+    version: &str,
+    is_static: bool,
+    targets: &[CMakeTarget],
+) -> Result<PathBuf, Box<dyn Error>> {
+    let directory = prefix.join("lib").join("cmake").join("OpenSSL");
+    std::fs::create_dir_all(&directory)?;
+
+    let mut imported = String::from(
+        "get_filename_component(_OPENSSL_PREFIX \"${CMAKE_CURRENT_LIST_DIR}/../../..\" ABSOLUTE)\n",
+    );
+    let kind = if is_static { "STATIC" } else { "SHARED" };
+    for target in targets {
+        imported.push_str(&format!(
+            "\nif(NOT TARGET OpenSSL::{name})\n  add_library(OpenSSL::{name} {kind} IMPORTED)\n  set_target_properties(OpenSSL::{name} PROPERTIES\n    IMPORTED_LOCATION \"{location}\"\n",
+            name = target.name,
+            kind = kind,
+            location = relative(prefix, &target.location)?
+        ));
+        if let Some(import_library) = &target.import_library {
+            imported.push_str(&format!(
+                "    IMPORTED_IMPLIB \"{}\"\n",
+                relative(prefix, import_library)?
+            ));
+        }
+        imported.push_str("    INTERFACE_INCLUDE_DIRECTORIES \"${_OPENSSL_PREFIX}/include\"\n");
+        if !target.link_libraries.is_empty() {
+            imported.push_str(&format!(
+                "    INTERFACE_LINK_LIBRARIES \"{}\"\n",
+                target.link_libraries.join(";")
+            ));
+        }
+        imported.push_str("  )\nendif()\n");
+    }
+    std::fs::write(directory.join("OpenSSLTargets.cmake"), imported)?;
+
+    let mut config = format!(
+        "include(\"${{CMAKE_CURRENT_LIST_DIR}}/OpenSSLTargets.cmake\")\n\nset(OPENSSL_FOUND TRUE)\nset(OPENSSL_VERSION \"{}\")\nset(OPENSSL_INCLUDE_DIR \"${{_OPENSSL_PREFIX}}/include\")\n",
+        version
+    );
+    for target in targets {
+        config.push_str(&format!(
+            "set(OPENSSL_{}_LIBRARY OpenSSL::{})\n",
+            target.name.to_uppercase(),
+            target.name
+        ));
+    }
+    // libssl first, it depends on libcrypto
+    let libraries = targets
+        .iter()
+        .rev()
+        .map(|target| format!("OpenSSL::{}", target.name))
+        .collect::<Vec<String>>();
+    config.push_str(&format!("set(OPENSSL_LIBRARIES {})\n", libraries.join(" ")));
+    std::fs::write(directory.join("OpenSSLConfig.cmake"), config)?;
+
+    // the same major version is compatible, as long as it is not older than requested.
+    // The letter of 1.1.1 releases is dropped, CMake only compares numbers
+    let package_version = version.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let major = package_version.split('.').next().unwrap_or(package_version);
+    let config_version = format!(
+        "set(PACKAGE_VERSION \"{version}\")\n\nif(PACKAGE_FIND_VERSION_MAJOR STREQUAL \"{major}\" AND NOT PACKAGE_VERSION VERSION_LESS PACKAGE_FIND_VERSION)\n  set(PACKAGE_VERSION_COMPATIBLE TRUE)\n  if(PACKAGE_FIND_VERSION VERSION_EQUAL PACKAGE_VERSION)\n    set(PACKAGE_VERSION_EXACT TRUE)\n  endif()\nelse()\n  set(PACKAGE_VERSION_COMPATIBLE FALSE)\nendif()\n",
+        version = package_version,
+        major = major
+    );
+    std::fs::write(directory.join("OpenSSLConfigVersion.cmake"), config_version)?;
+    Ok(directory)
+}
+
+/// The path in CMake syntax, relative to the install prefix
+fn relative(prefix: &Path, path: &Path) -> Result<String, Box<dyn Error>> {
+    let relative = path.strip_prefix(prefix).map_err(|_| {
+        format!(
+            "{} is not in the install prefix {}",
+            path.display(),
+            prefix.display()
+        )
+    })?;
+    Ok(format!(
+        "${{_OPENSSL_PREFIX}}/{}",
+        relative.to_string_lossy().replace('\\', "/")
+    ))
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use shared_library_builder::{Library, LibraryCompilationContext};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZlibLinkage {
+    /// Link libcrypto against zlib
+    Static,
+    /// Load zlib at runtime when compression is first used
+    Dynamic,
+}
+
+/// zlib compression support, backed by a library that shared-library-builder compiles first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZlibDependency {
+    library: Box<dyn Library>,
+    linkage: ZlibLinkage,
+}
+
+impl ZlibDependency {
+    pub fn new(library: impl Into<Box<dyn Library>>, linkage: ZlibLinkage) -> Self {
+        Self {
+            library: library.into(),
+            linkage,
+        }
+    }
+
+    pub fn library(&self) -> &dyn Library {
+        self.library.as_ref()
+    }
+
+    pub fn configure_args(&self, context: &LibraryCompilationContext) -> Vec<String> {
+        let mut args = vec![match self.linkage {
+            ZlibLinkage::Static => "zlib".to_string(),
+            ZlibLinkage::Dynamic => "zlib-dynamic".to_string(),
+        }];
+
+        if let Some(include) = first(self.library.native_library_include_headers(context)) {
+            args.push(format!("--with-zlib-include={}", include.display()));
+        }
+        if let Some(lib) = first(self.library.native_library_linker_libraries(context)) {
+            args.push(format!("--with-zlib-lib={}", lib.display()));
+        }
+        args
+    }
+}
+
+fn first(paths: Vec<PathBuf>) -> Option<PathBuf> {
+    paths.into_iter().next()
+}
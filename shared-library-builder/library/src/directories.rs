@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Directories that OpenSSL compiles into the library and looks up at runtime.
+/// Relative values are resolved against the install prefix; absolute values
+/// such as `/etc/ssl` keep build-machine paths out of deployable artifacts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstallDirectories {
+    /// Where `openssl.cnf` and the default certificate store live
+    openssldir: Option<String>,
+    /// Where dynamic engines are loaded from
+    enginesdir: Option<String>,
+    /// Where OpenSSL 3 providers are loaded from
+    modulesdir: Option<String>,
+}
+
+impl InstallDirectories {
+    pub fn with_openssldir(mut self, openssldir: impl Into<String>) -> Self {
+        self.openssldir = Some(openssldir.into());
+        self
+    }
+
+    pub fn with_enginesdir(mut self, enginesdir: impl Into<String>) -> Self {
+        self.enginesdir = Some(enginesdir.into());
+        self
+    }
+
+    pub fn with_modulesdir(mut self, modulesdir: impl Into<String>) -> Self {
+        self.modulesdir = Some(modulesdir.into());
+        self
+    }
+
+    pub fn openssldir(&self, prefix: &Path) -> String {
+        self.openssldir
+            .clone()
+            .unwrap_or_else(|| prefix.display().to_string())
+    }
+
+    pub fn enginesdir(&self) -> Option<&str> {
+        self.enginesdir.as_deref()
+    }
+
+    pub fn modulesdir(&self) -> Option<&str> {
+        self.modulesdir.as_deref()
+    }
+
+    /// Overrides of the makefile variables, passed to every make invocation. They are compiled
+    /// into libcrypto as absolute paths, relative values resolved against the install prefix of
+    /// the build machine; relocated libraries find their engines and providers through
+    /// `OPENSSL_ENGINES` and `OPENSSL_MODULES`, see [`crate::OpenSSLLibrary::runtime_vars`]
+    pub fn make_variables(&self, prefix: &Path) -> Vec<String> {
+        let mut variables = vec![];
+        if let Some(enginesdir) = &self.enginesdir {
+            variables.push(format!(
+                "ENGINESDIR={}",
+                Self::resolve(prefix, enginesdir).display()
+            ));
+        }
+        if let Some(modulesdir) = &self.modulesdir {
+            variables.push(format!(
+                "MODULESDIR={}",
+                Self::resolve(prefix, modulesdir).display()
+            ));
+        }
+        variables
+    }
+
+    pub fn resolve(prefix: &Path, directory: &str) -> PathBuf {
+        prefix.join(directory)
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::process::Command;
+
+/// Variables a compiler toolchain needs to run at all, on any host
+const DEFAULT_ALLOWED: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "TMPDIR",
+    "TMP",
+    "TEMP",
+    // Windows
+    "SystemRoot",
+    "SystemDrive",
+    "windir",
+    "ComSpec",
+    "PATHEXT",
+    "ProgramFiles",
+    "ProgramFiles(x86)",
+    "ProgramData",
+    "LOCALAPPDATA",
+    "APPDATA",
+    "USERPROFILE",
+    "NUMBER_OF_PROCESSORS",
+    "PROCESSOR_ARCHITECTURE",
+    // macOS
+    "DEVELOPER_DIR",
+];
+
+/// Runs Configure and make with a minimal environment instead of everything the builder
+/// inherited, so that stray `CFLAGS`, `CC` or `PERL5LIB` of a developer machine can not
+/// change the build. Only the allowed variables are passed through, plus the explicitly
+/// set ones and those the builder sets itself, such as the MSVC or Android toolchain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HermeticEnvironment {
+    allowed: BTreeSet<String>,
+    variables: BTreeMap<String, String>,
+}
+
+impl Default for HermeticEnvironment {
+    fn default() -> Self {
+        Self {
+            allowed: DEFAULT_ALLOWED
+                .iter()
+                .map(|variable| variable.to_string())
+                .collect(),
+            variables: Default::default(),
+        }
+    }
+}
+
+impl HermeticEnvironment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pass the variable through from the environment of the builder
+    pub fn allow(mut self, variable: impl Into<String>) -> Self {
+        self.allowed.insert(variable.into());
+        self
+    }
+
+    /// Set the variable to the value, whatever the environment of the builder contains
+    pub fn with_variable(mut self, variable: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.insert(variable.into(), value.into());
+        self
+    }
+
+    /// Replace the inherited environment of a new command. Has to be applied before
+    /// anything else sets a variable of the command
+    pub fn apply(&self, command: &mut Command) {
+        command.env_clear();
+        // variable names are case insensitive on Windows
+        for (key, value) in std::env::vars_os() {
+            let key_name = key.to_string_lossy();
+            let is_allowed = self.allowed.iter().any(|allowed| {
+                if cfg!(target_os = "windows") {
+                    allowed.eq_ignore_ascii_case(&key_name)
+                } else {
+                    *allowed == key_name
+                }
+            });
+            if is_allowed {
+                command.env(&key, value);
+            }
+        }
+        command.envs(&self.variables);
+    }
+}
@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::process::ExitStatus;
+
+use crate::MissingRequirement;
+
+/// Why compiling OpenSSL failed
+#[derive(Debug)]
+pub enum OpenSSLBuildError {
+    /// There is no Configure target for the target triple
+    UnsupportedTarget(String),
+    /// The requested options can not be built, for example the fips provider with OpenSSL 1.1.1
+    UnsupportedConfiguration(String),
+    /// A program needed for the build is not installed
+    ToolMissing(String),
+    /// Everything the pre-flight check could not find
+    MissingRequirements(Vec<MissingRequirement>),
+    /// An environment variable needed for the build is not set
+    EnvironmentMissing(String),
+    ConfigureFailed {
+        library: String,
+        status: ExitStatus,
+    },
+    MakeFailed {
+        library: String,
+        status: ExitStatus,
+    },
+    TestsFailed {
+        library: String,
+        status: ExitStatus,
+    },
+    InstallFailed {
+        library: String,
+        status: ExitStatus,
+    },
+    /// Rewriting the install names or sonames of the installed libraries failed
+    PostProcessingFailed(String),
+    /// The built library could not be loaded or reports an unexpected version
+    VerificationFailed(String),
+    Io(std::io::Error),
+}
+
+impl Display for OpenSSLBuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedTarget(target) => write!(f, "OpenSSL can not be built for {}", target),
+            Self::UnsupportedConfiguration(reason) => write!(f, "{}", reason),
+            Self::ToolMissing(tool) => write!(f, "Could not find `{}`", tool),
+            Self::MissingRequirements(requirements) => {
+                write!(
+                    f,
+                    "OpenSSL can not be built, because some requirements are missing:"
+                )?;
+                for requirement in requirements {
+                    write!(f, "\n  - {}", requirement)?;
+                }
+                Ok(())
+            }
+            Self::EnvironmentMissing(reason) => write!(f, "{}", reason),
+            Self::ConfigureFailed { library, status } => {
+                write!(f, "Could not configure {} ({})", library, status)
+            }
+            Self::MakeFailed { library, status } => {
+                write!(f, "Could not compile {} ({})", library, status)
+            }
+            Self::TestsFailed { library, status } => {
+                write!(f, "The test suite of {} failed ({})", library, status)
+            }
+            Self::InstallFailed { library, status } => {
+                write!(f, "Could not install {} ({})", library, status)
+            }
+            Self::PostProcessingFailed(reason) => write!(f, "{}", reason),
+            Self::VerificationFailed(reason) => write!(f, "Verification failed: {}", reason),
+            Self::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for OpenSSLBuildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for OpenSSLBuildError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
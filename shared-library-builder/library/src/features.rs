@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Optional parts of OpenSSL that can be left out at Configure time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum OpenSSLFeature {
+    Deprecated,
+    Engine,
+    Comp,
+    Dso,
+    /// Assembly optimisations, disabling them removes the NASM requirement on Windows
+    Asm,
+}
+
+impl OpenSSLFeature {
+    pub fn configure_name(&self) -> &str {
+        match self {
+            Self::Deprecated => "deprecated",
+            Self::Engine => "engine",
+            Self::Comp => "comp",
+            Self::Dso => "dso",
+            Self::Asm => "asm",
+        }
+    }
+
+    pub fn disable_flag(&self) -> String {
+        format!("no-{}", self.configure_name())
+    }
+}
+
+/// Protocol versions that can be compiled out, so the library cannot negotiate them at all
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum OpenSSLProtocol {
+    Ssl3,
+    Tls1,
+    Tls1_1,
+    Tls1_2,
+    Dtls,
+    Dtls1,
+    Dtls1_2,
+}
+
+impl OpenSSLProtocol {
+    /// Protocols that are considered insecure today
+    pub fn legacy() -> Vec<Self> {
+        vec![Self::Ssl3, Self::Tls1, Self::Tls1_1, Self::Dtls1]
+    }
+
+    pub fn configure_name(&self) -> &str {
+        match self {
+            Self::Ssl3 => "ssl3",
+            Self::Tls1 => "tls1",
+            Self::Tls1_1 => "tls1_1",
+            Self::Tls1_2 => "tls1_2",
+            Self::Dtls => "dtls",
+            Self::Dtls1 => "dtls1",
+            Self::Dtls1_2 => "dtls1_2",
+        }
+    }
+
+    pub fn disable_flag(&self) -> String {
+        format!("no-{}", self.configure_name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum OpenSSLAlgorithm {
+    Aria,
+    Bf,
+    Blake2,
+    Camellia,
+    Cast,
+    Des,
+    Idea,
+    Md2,
+    Md4,
+    Mdc2,
+    Rc2,
+    Rc4,
+    Rc5,
+    Rmd160,
+    Seed,
+    Sm2,
+    Sm3,
+    Sm4,
+    WeakSslCiphers,
+    Whirlpool,
+}
+
+impl OpenSSLAlgorithm {
+    pub fn configure_name(&self) -> &str {
+        match self {
+            Self::Aria => "aria",
+            Self::Bf => "bf",
+            Self::Blake2 => "blake2",
+            Self::Camellia => "camellia",
+            Self::Cast => "cast",
+            Self::Des => "des",
+            Self::Idea => "idea",
+            Self::Md2 => "md2",
+            Self::Md4 => "md4",
+            Self::Mdc2 => "mdc2",
+            Self::Rc2 => "rc2",
+            Self::Rc4 => "rc4",
+            Self::Rc5 => "rc5",
+            Self::Rmd160 => "rmd160",
+            Self::Seed => "seed",
+            Self::Sm2 => "sm2",
+            Self::Sm3 => "sm3",
+            Self::Sm4 => "sm4",
+            Self::WeakSslCiphers => "weak-ssl-ciphers",
+            Self::Whirlpool => "whirlpool",
+        }
+    }
+}
+
+/// Algorithms to switch on or off relative to OpenSSL's default set.
+/// Md2, Rc5 and weak-ssl-ciphers are disabled upstream unless enabled explicitly
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Algorithms {
+    enabled: BTreeSet<OpenSSLAlgorithm>,
+    disabled: BTreeSet<OpenSSLAlgorithm>,
+}
+
+impl Algorithms {
+    pub fn enable(mut self, algorithm: OpenSSLAlgorithm) -> Self {
+        self.disabled.remove(&algorithm);
+        self.enabled.insert(algorithm);
+        self
+    }
+
+    pub fn disable(mut self, algorithm: OpenSSLAlgorithm) -> Self {
+        self.enabled.remove(&algorithm);
+        self.disabled.insert(algorithm);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.enabled.is_empty() && self.disabled.is_empty()
+    }
+
+    pub fn configure_flags(&self) -> Vec<String> {
+        let enabled = self
+            .enabled
+            .iter()
+            .map(|algorithm| format!("enable-{}", algorithm.configure_name()));
+        let disabled = self
+            .disabled
+            .iter()
+            .map(|algorithm| format!("no-{}", algorithm.configure_name()));
+        enabled.chain(disabled).collect()
+    }
+}
+
+/// Engines that can be built into or left out of libcrypto, or built as loadable modules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum OpenSSLEngine {
+    /// The Windows CryptoAPI engine
+    Capi,
+    /// The Linux kernel crypto API engine
+    Afalg,
+    /// The VIA PadLock engine of x86 processors
+    Padlock,
+}
+
+impl OpenSSLEngine {
+    pub fn configure_name(&self) -> &str {
+        match self {
+            Self::Capi => "capieng",
+            Self::Afalg => "afalgeng",
+            Self::Padlock => "padlockeng",
+        }
+    }
+
+    /// Whether the engine can be built for the target, an error message otherwise
+    pub fn check_target(&self, target: &str) -> Result<(), String> {
+        let supported = match self {
+            Self::Capi => target.contains("-windows"),
+            Self::Afalg => target.contains("-linux-") && !target.contains("android"),
+            Self::Padlock => target.starts_with("x86_64"),
+        };
+        if supported {
+            Ok(())
+        } else {
+            Err(format!(
+                "The {} engine can not be built for {}",
+                self.configure_name(),
+                target
+            ))
+        }
+    }
+}
+
+/// Engines to switch on or off relative to OpenSSL's default set, which builds the engines
+/// the target supports
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Engines {
+    enabled: BTreeSet<OpenSSLEngine>,
+    disabled: BTreeSet<OpenSSLEngine>,
+}
+
+impl Engines {
+    pub fn enable(mut self, engine: OpenSSLEngine) -> Self {
+        self.disabled.remove(&engine);
+        self.enabled.insert(engine);
+        self
+    }
+
+    pub fn disable(mut self, engine: OpenSSLEngine) -> Self {
+        self.enabled.remove(&engine);
+        self.disabled.insert(engine);
+        self
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &OpenSSLEngine> {
+        self.enabled.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.enabled.is_empty() && self.disabled.is_empty()
+    }
+
+    pub fn configure_flags(&self) -> Vec<String> {
+        let enabled = self
+            .enabled
+            .iter()
+            .map(|engine| format!("enable-{}", engine.configure_name()));
+        let disabled = self
+            .disabled
+            .iter()
+            .map(|engine| format!("no-{}", engine.configure_name()));
+        enabled.chain(disabled).collect()
+    }
+}
@@ -0,0 +1,230 @@
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::process::Command;
+
+/// Stored in the build directory after a successful build
+pub const FINGERPRINT_FILE: &str = "openssl-build.fingerprint";
+/// Stored in the build directory after a successful Configure
+pub const CONFIGURE_STAMP_FILE: &str = "openssl-configure.stamp";
+
+/// Identifies everything that influences the result of a build: the source revision,
+/// the Configure invocation with its environment, the target, the profile and the options
+/// of the steps after make, such as stripping, signing or renaming the libraries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildFingerprint {
+    value: String,
+    configuration: String,
+    source_revision: Option<String>,
+}
+
+impl BuildFingerprint {
+    pub fn new(
+        configure: &Command,
+        source_revision: Option<String>,
+        target: &str,
+        profile: &str,
+        post_processing: &str,
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(target.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(profile.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(configure.get_program().to_string_lossy().as_bytes());
+        for arg in configure.get_args() {
+            hasher.update(b"\0");
+            hasher.update(arg.to_string_lossy().as_bytes());
+        }
+        let mut envs = configure
+            .get_envs()
+            .map(|(key, value)| {
+                format!(
+                    "{}={}",
+                    key.to_string_lossy(),
+                    value
+                        .map(|value| value.to_string_lossy())
+                        .unwrap_or_default()
+                )
+            })
+            .collect::<Vec<String>>();
+        envs.sort();
+        for env in envs {
+            hasher.update(b"\0");
+            hasher.update(env.as_bytes());
+        }
+        let configuration = to_hex(&hasher.finalize());
+
+        let mut hasher = Sha256::new();
+        hasher.update(configuration.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(source_revision.as_deref().unwrap_or("").as_bytes());
+        hasher.update(b"\0");
+        hasher.update(post_processing.as_bytes());
+
+        Self {
+            value: to_hex(&hasher.finalize()),
+            configuration,
+            source_revision,
+        }
+    }
+
+    /// A build can only be skipped when the revision of the sources is known,
+    /// local working trees are always handed to make
+    pub fn is_reusable(&self) -> bool {
+        self.source_revision.is_some()
+    }
+
+    pub fn matches(&self, file: &Path) -> bool {
+        std::fs::read_to_string(file)
+            .map(|stored| stored.trim() == self.value)
+            .unwrap_or(false)
+    }
+
+    pub fn write(&self, file: &Path) -> std::io::Result<()> {
+        std::fs::write(file, &self.value)
+    }
+
+    /// Whether the build directory was configured with the same options, ignoring the sources
+    pub fn configuration_matches(&self, stamp_file: &Path) -> bool {
+        std::fs::read_to_string(stamp_file)
+            .map(|stored| stored.trim() == self.configuration)
+            .unwrap_or(false)
+    }
+
+    pub fn write_configuration(&self, stamp_file: &Path) -> std::io::Result<()> {
+        std::fs::write(stamp_file, &self.configuration)
+    }
+}
+
+/// The environment variables of Configure that change the compiled libraries. The others, such
+/// as `PATH` or the variables of an MSVC developer prompt, describe the machine
+const OPTION_VARIABLES: &[&str] = &[
+    "CFLAGS", "CPPFLAGS", "CXXFLAGS", "LDFLAGS", "CC", "CXX", "AR", "RANLIB", "LD",
+];
+
+/// A short hash of the Configure arguments, the compiler and linker variables of its environment,
+/// the target and the profile that is the same on every machine: the program and the local
+/// paths, such as the install prefix, are left out. Prebuilt release assets are looked up by it
+pub fn options_hash(
+    configure: &Command,
+    target: &str,
+    profile: &str,
+    local_paths: &[&Path],
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(target.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(profile.as_bytes());
+    let strip_local_paths = |value: &str| {
+        let mut value = value.to_string();
+        for path in local_paths {
+            value = value.replace(path.to_string_lossy().as_ref(), "");
+        }
+        value
+    };
+    for arg in configure.get_args() {
+        hasher.update(b"\0");
+        hasher.update(strip_local_paths(&arg.to_string_lossy()).as_bytes());
+    }
+    let mut envs = configure
+        .get_envs()
+        .filter(|(key, _)| OPTION_VARIABLES.contains(&key.to_string_lossy().as_ref()))
+        .filter_map(|(key, value)| {
+            Some(format!(
+                "{}={}",
+                key.to_string_lossy(),
+                strip_local_paths(&value?.to_string_lossy())
+            ))
+        })
+        .collect::<Vec<String>>();
+    envs.sort();
+    for env in envs {
+        hasher.update(b"\0");
+        hasher.update(env.as_bytes());
+    }
+    to_hex(&hasher.finalize())[..16].to_string()
+}
+
+/// The commit of a git checkout
+pub fn git_revision(directory: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .current_dir(directory)
+        .arg("rev-parse")
+        .arg("HEAD")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Check the `configdata.pm` written by Configure for the expected Configure target,
+/// which catches build trees configured by hand or by older versions of the builder
+pub fn is_configured_for(build_directory: &Path, configure_target: &str) -> bool {
+    std::fs::read_to_string(build_directory.join("configdata.pm"))
+        .map(|configdata| {
+            // OpenSSL 3 quotes the keys, 1.1.1 does not
+            configdata.contains(&format!("\"target\" => \"{}\"", configure_target))
+                || configdata.contains(&format!("target => \"{}\"", configure_target))
+        })
+        .unwrap_or(false)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configure(build_directory: &str) -> Command {
+        let mut command = Command::new("perl");
+        command
+            .arg(format!("{}/openssl/Configure", build_directory))
+            .arg(format!("--prefix={}/build", build_directory))
+            .arg("no-shared")
+            .env(
+                "CFLAGS",
+                format!("-ffile-prefix-map={}=build", build_directory),
+            )
+            .env("PATH", build_directory);
+        command
+    }
+
+    #[test]
+    fn options_hash_ignores_local_paths() {
+        let first = options_hash(
+            &configure("/home/first"),
+            "x86_64-unknown-linux-gnu",
+            "release",
+            &[Path::new("/home/first")],
+        );
+        let second = options_hash(
+            &configure("/tmp/second"),
+            "x86_64-unknown-linux-gnu",
+            "release",
+            &[Path::new("/tmp/second")],
+        );
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 16);
+    }
+
+    #[test]
+    fn options_hash_covers_options_and_flags() {
+        let local_paths = [Path::new("/home/first")];
+        let hash = |command: &Command, profile: &str| {
+            options_hash(command, "x86_64-unknown-linux-gnu", profile, &local_paths)
+        };
+        let base = configure("/home/first");
+        let mut with_option = configure("/home/first");
+        with_option.arg("no-asm");
+        let mut with_flags = configure("/home/first");
+        with_flags.env("LDFLAGS", "-static-libgcc");
+
+        assert_ne!(hash(&base, "release"), hash(&base, "debug"));
+        assert_ne!(hash(&base, "release"), hash(&with_option, "release"));
+        assert_ne!(hash(&base, "release"), hash(&with_flags, "release"));
+    }
+}
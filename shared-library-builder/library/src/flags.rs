@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+/// Compiler and linker flags added to `CFLAGS` and `LDFLAGS` of Configure, after the flags
+/// of the Configure target so that they take precedence, for example `-march=x86-64-v3`,
+/// `-g` or `-fstack-protector-strong`. Restricted to a profile or target when given
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompilerFlags {
+    cflags: Vec<String>,
+    ldflags: Vec<String>,
+    profile: Option<String>,
+    target: Option<String>,
+}
+
+impl CompilerFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cflags(mut self, flags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.cflags
+            .extend(flags.into_iter().map(|flag| flag.into()));
+        self
+    }
+
+    pub fn ldflags(mut self, flags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.ldflags
+            .extend(flags.into_iter().map(|flag| flag.into()));
+        self
+    }
+
+    /// Only for the profile, `debug` or `release`
+    pub fn for_profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Only for the target triple
+    pub fn for_target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn applies_to(&self, profile: &str, target: &str) -> bool {
+        self.profile
+            .as_ref()
+            .map(|selected| selected == profile)
+            .unwrap_or(true)
+            && self
+                .target
+                .as_ref()
+                .map(|selected| selected == target)
+                .unwrap_or(true)
+    }
+
+    pub fn get_cflags(&self) -> &[String] {
+        &self.cflags
+    }
+
+    pub fn get_ldflags(&self) -> &[String] {
+        &self.ldflags
+    }
+}
+
+/// The optimization level the libraries are compiled with, instead of the one of the Configure
+/// target and profile
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OptimizationLevel {
+    /// No optimization, `-O0`
+    None,
+    /// `-O1`
+    Basic,
+    /// `-O2`
+    Default,
+    /// `-O3`
+    Aggressive,
+    /// Optimize for size, `-Os`, for mobile and embedded targets
+    Size,
+    /// Optimize for size even at the cost of speed, `-Oz`. Requires Clang or GCC 12
+    MinSize,
+}
+
+impl OptimizationLevel {
+    /// The value of `OPT_LEVEL`
+    pub fn opt_level(&self) -> &'static str {
+        match self {
+            Self::None => "0",
+            Self::Basic => "1",
+            Self::Default => "2",
+            Self::Aggressive => "3",
+            Self::Size => "s",
+            Self::MinSize => "z",
+        }
+    }
+
+    /// The compiler flag, for cl.exe when `is_msvc`
+    pub fn flag(&self, is_msvc: bool) -> &'static str {
+        if is_msvc {
+            match self {
+                Self::None => "/Od",
+                Self::Basic | Self::Size | Self::MinSize => "/O1",
+                Self::Default | Self::Aggressive => "/O2",
+            }
+        } else {
+            match self {
+                Self::None => "-O0",
+                Self::Basic => "-O1",
+                Self::Default => "-O2",
+                Self::Aggressive => "-O3",
+                Self::Size => "-Os",
+                Self::MinSize => "-Oz",
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unrestricted_flags_apply_everywhere() {
+        let flags = CompilerFlags::new().cflags(["-g"]);
+        assert!(flags.applies_to("debug", "x86_64-unknown-linux-gnu"));
+        assert!(flags.applies_to("release", "aarch64-apple-darwin"));
+    }
+
+    #[test]
+    fn flags_restricted_to_a_profile_and_target() {
+        let flags = CompilerFlags::new()
+            .cflags(["-march=x86-64-v3"])
+            .for_profile("release")
+            .for_target("x86_64-unknown-linux-gnu");
+        assert!(flags.applies_to("release", "x86_64-unknown-linux-gnu"));
+        assert!(!flags.applies_to("debug", "x86_64-unknown-linux-gnu"));
+        assert!(!flags.applies_to("release", "aarch64-unknown-linux-gnu"));
+    }
+
+    #[test]
+    fn optimization_levels() {
+        assert_eq!(OptimizationLevel::Size.opt_level(), "s");
+        assert_eq!(OptimizationLevel::Size.flag(false), "-Os");
+        assert_eq!(OptimizationLevel::MinSize.flag(false), "-Oz");
+        assert_eq!(OptimizationLevel::MinSize.flag(true), "/O1");
+        assert_eq!(OptimizationLevel::Aggressive.flag(true), "/O2");
+        assert_eq!(OptimizationLevel::None.flag(true), "/Od");
+    }
+}
@@ -0,0 +1,35 @@
+use shared_library_builder::{Library, LibraryCompilationContext};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::targets::android_abi;
+use crate::{OpenSSLBuildError, OpenSSLLibrary};
+
+/// Builds the library for the Android targets of the contexts and copies libcrypto.so and
+/// libssl.so into `<destination>/<abi>/`, the `jniLibs` layout of an Android project,
+/// for example `app/src/main/jniLibs/arm64-v8a/libssl.so`. Returns the copied libraries
+pub fn compile_jni_libs(
+    library: &OpenSSLLibrary,
+    contexts: &[LibraryCompilationContext],
+    destination: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut copied = vec![];
+    for context in contexts {
+        let target = context.target().to_string();
+        let abi = android_abi(&target).ok_or(OpenSSLBuildError::UnsupportedTarget(target))?;
+        library.compile(context)?;
+
+        let abi_directory = destination.join(abi);
+        std::fs::create_dir_all(&abi_directory)?;
+        let (crypto_library, ssl_library) = library.compiled_libraries(context);
+        for compiled in &[crypto_library, ssl_library] {
+            let file_name = compiled
+                .file_name()
+                .ok_or_else(|| format!("{} has no file name", compiled.display()))?;
+            let copy = abi_directory.join(file_name);
+            std::fs::copy(compiled, &copy)?;
+            copied.push(copy);
+        }
+    }
+    Ok(copied)
+}
@@ -1,18 +1,110 @@
+mod android;
+mod bindings;
+mod build_support;
+mod cache;
+mod checksums;
+mod cmake;
+mod dependencies;
+mod directories;
+mod environment;
+mod error;
+mod features;
+mod fingerprint;
+mod flags;
+mod jni_libs;
+mod licenses;
+mod linkage;
+mod lock;
+mod log;
+mod metadata;
+mod msvc;
+mod naming;
+mod nasm;
 mod openssl_library;
+mod package;
+mod patch;
+mod pkcs11;
+mod plan;
+mod prebuilt;
+mod publish;
+mod relocate;
+mod report;
+mod reproducible;
+mod requirements;
+mod retry;
+mod sanitizer;
+mod sbom;
+mod signing;
+mod source;
+mod strip;
+mod symbols;
+mod targets;
+mod tools;
+mod universal;
+mod verify;
+mod version;
+mod visibility;
+mod xcframework;
 
-use crate::openssl_library::OpenSSLLibrary;
-use shared_library_builder::{GitLocation, LibraryLocation};
+pub use crate::android::AndroidNdk;
+#[cfg(feature = "bindgen")]
+pub use crate::bindings::generate_bindings;
+pub use crate::bindings::BINDINGS_FILE;
+pub use crate::build_support::build_and_link;
+pub use crate::cache::DownloadCache;
+pub use crate::checksums::{verify_checksums, CHECKSUMS_FILE};
+pub use crate::cmake::{write_cmake_package, CMakeTarget};
+pub use crate::dependencies::{ZlibDependency, ZlibLinkage};
+pub use crate::directories::InstallDirectories;
+pub use crate::environment::HermeticEnvironment;
+pub use crate::error::OpenSSLBuildError;
+pub use crate::features::{
+    Algorithms, Engines, OpenSSLAlgorithm, OpenSSLEngine, OpenSSLFeature, OpenSSLProtocol,
+};
+pub use crate::flags::{CompilerFlags, OptimizationLevel};
+pub use crate::log::LogLevel;
+pub use crate::metadata::{BuildMetadata, BuiltFile, BUILD_METADATA_FILE};
+pub use crate::msvc::MsvcToolchain;
+pub use crate::naming::LibraryNaming;
+pub use crate::nasm::NasmRelease;
+pub use crate::openssl_library::OpenSSLLibrary;
+pub use crate::package::PackageManifest;
+pub use crate::patch::SourcePatch;
+pub use crate::pkcs11::{Pkcs11Dependency, Pkcs11Module};
+pub use crate::plan::{BuildPlan, PlannedCommand};
+pub use crate::prebuilt::{PrebuiltAsset, PrebuiltBinaries};
+pub use crate::publish::GitHubRelease;
+pub use crate::report::{BuildReport, BuildStage, StageTiming};
+pub use crate::requirements::MissingRequirement;
+pub use crate::retry::RetryPolicy;
+pub use crate::sanitizer::Sanitizer;
+pub use crate::sbom::SBOM_FILE;
+pub use crate::signing::SigningConfig;
+pub use crate::source::{GitCheckout, GitFetchSettings, GitReference, SourceArchive};
+pub use crate::symbols::{REQUIRED_CRYPTO_SYMBOLS, REQUIRED_SSL_SYMBOLS};
+pub use crate::targets::{configure_target, supported_targets, SUPPORTED_TARGETS};
+pub use crate::tools::BuildTool;
+pub use crate::universal::universal_prefix;
+pub use crate::verify::verify_existing_libraries;
+pub use crate::version::OpenSSLVersion;
+pub use crate::visibility::ExportedSymbols;
+pub use crate::xcframework::xcframework_directory;
 
+/// `Some("latest")` resolves to the newest release of feenkcom/libopenssl when the library
+/// is compiled, if that fails the libraries are compiled from sources
 pub fn libopenssl(binary_version: Option<impl Into<String>>) -> OpenSSLLibrary {
-    OpenSSLLibrary::default().with_release_location(binary_version.map(|version| {
-        LibraryLocation::Git(GitLocation::github("feenkcom", "libopenssl").tag(version))
-    }))
+    OpenSSLLibrary::default().with_binary_version(binary_version)
 }
 
 pub fn libssl(binary_version: Option<impl Into<String>>) -> OpenSSLLibrary {
     libopenssl(binary_version).be_ssl()
 }
 
+/// libcrypto and libssl from a single compilation
+pub fn libopenssl_both(binary_version: Option<impl Into<String>>) -> OpenSSLLibrary {
+    libopenssl(binary_version).be_both()
+}
+
 pub fn libcrypto(binary_version: Option<impl Into<String>>) -> OpenSSLLibrary {
     libopenssl(binary_version).be_crypto()
 }
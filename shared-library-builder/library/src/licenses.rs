@@ -0,0 +1,32 @@
+use std::path::{Path, PathBuf};
+
+/// License and notice files looked for in the root of a source tree
+const LICENSE_FILES: &[&str] = &[
+    "LICENSE",
+    "LICENSE.txt",
+    "LICENSE.md",
+    "COPYING",
+    "NOTICE",
+    "NOTICE.txt",
+];
+
+/// Copy the license files of a source tree into the destination as `<name>-<FILE>`,
+/// for example `openssl-LICENSE.txt`. Returns the copied files
+pub fn collect_licenses(
+    name: &str,
+    source_directory: &Path,
+    destination: &Path,
+) -> std::io::Result<Vec<PathBuf>> {
+    let mut collected = vec![];
+    for file_name in LICENSE_FILES {
+        let license = source_directory.join(file_name);
+        if !license.is_file() {
+            continue;
+        }
+        std::fs::create_dir_all(destination)?;
+        let copy = destination.join(format!("{}-{}", name, file_name));
+        std::fs::copy(&license, &copy)?;
+        collected.push(copy);
+    }
+    Ok(collected)
+}
@@ -0,0 +1,123 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::targets::is_msvc;
+use crate::OpenSSLBuildError;
+
+/// The shared libraries a library depends on that can not be found, either next to it
+/// or where the loader of the platform looks for them. Uses `otool -L` on Apple targets,
+/// `dumpbin /dependents` for MSVC and `ldd` otherwise, so it only works for the host
+pub fn unresolved_dependencies(
+    library: &Path,
+    target: &str,
+) -> Result<Vec<String>, OpenSSLBuildError> {
+    let directory = library.parent().unwrap_or_else(|| Path::new("."));
+    if target.contains("-apple-") {
+        otool_unresolved(library, directory)
+    } else if is_msvc(target) {
+        dumpbin_unresolved(library, directory)
+    } else {
+        ldd_unresolved(library, directory)
+    }
+}
+
+/// Check that every dependency of the libraries resolves, reporting all missing ones at once
+pub fn check_dependencies(libraries: &[&Path], target: &str) -> Result<(), OpenSSLBuildError> {
+    let mut unresolved = vec![];
+    for library in libraries {
+        for dependency in unresolved_dependencies(library, target)? {
+            unresolved.push(format!("{} (needed by {})", dependency, library.display()));
+        }
+    }
+    if unresolved.is_empty() {
+        return Ok(());
+    }
+    Err(OpenSSLBuildError::VerificationFailed(format!(
+        "Unresolved dependencies: {}",
+        unresolved.join(", ")
+    )))
+}
+
+fn otool_unresolved(library: &Path, directory: &Path) -> Result<Vec<String>, OpenSSLBuildError> {
+    let output = tool_output(Command::new("otool").arg("-L").arg(library), "otool")?;
+    // the first line names the library itself, the rest are `<install name> (compatibility ...)`
+    Ok(output
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.trim().split(" (").next())
+        .filter(|install_name| !install_name.is_empty())
+        .filter(|install_name| {
+            let resolved = if let Some(name) = install_name
+                .strip_prefix("@rpath/")
+                .or_else(|| install_name.strip_prefix("@loader_path/"))
+            {
+                directory.join(name)
+            } else {
+                Path::new(install_name).to_path_buf()
+            };
+            // system libraries live in the dyld shared cache, not on disk
+            let is_system =
+                install_name.starts_with("/usr/lib/") || install_name.starts_with("/System/");
+            !is_system && !install_name.starts_with("@executable_path/") && !resolved.exists()
+        })
+        .map(|install_name| install_name.to_string())
+        .collect())
+}
+
+fn dumpbin_unresolved(library: &Path, directory: &Path) -> Result<Vec<String>, OpenSSLBuildError> {
+    which::which("dumpbin").map_err(|_| OpenSSLBuildError::ToolMissing("dumpbin".to_string()))?;
+    let output = tool_output(
+        Command::new("dumpbin")
+            .arg("/nologo")
+            .arg("/dependents")
+            .arg(library),
+        "dumpbin",
+    )?;
+    let system = std::env::var_os("SystemRoot")
+        .map(|root| Path::new(&root).join("System32"))
+        .unwrap_or_default();
+    Ok(output
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| line.to_lowercase().ends_with(".dll"))
+        // API sets are resolved by the loader and do not exist as files
+        .filter(|dll| !dll.to_lowercase().starts_with("api-ms-win-"))
+        .filter(|dll| {
+            !directory.join(dll).exists()
+                && !system.join(dll).exists()
+                && which::which(dll).is_err()
+        })
+        .map(|dll| dll.to_string())
+        .collect())
+}
+
+fn ldd_unresolved(library: &Path, directory: &Path) -> Result<Vec<String>, OpenSSLBuildError> {
+    // libssl finds libcrypto next to it, the way an application bundling both would
+    let output = tool_output(
+        Command::new("ldd")
+            .env("LD_LIBRARY_PATH", directory)
+            .arg(library),
+        "ldd",
+    )?;
+    // `libfoo.so.1 => not found`
+    Ok(output
+        .lines()
+        .filter(|line| line.contains("not found"))
+        .filter_map(|line| line.split("=>").next())
+        .map(|name| name.trim().to_string())
+        .collect())
+}
+
+fn tool_output(command: &mut Command, tool: &str) -> Result<String, OpenSSLBuildError> {
+    let output = command
+        .output()
+        .map_err(|_| OpenSSLBuildError::ToolMissing(tool.to_string()))?;
+    if !output.status.success() {
+        return Err(OpenSSLBuildError::VerificationFailed(format!(
+            "{} failed: {}",
+            tool,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
@@ -0,0 +1,45 @@
+use fs2::FileExt;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+use crate::log::LogLevel;
+
+/// An advisory lock on a directory, held until dropped. Concurrent builder processes
+/// (or cargo build scripts) that target the same directory wait for each other,
+/// after which the waiting one can reuse what the first one produced
+pub struct DirectoryLock {
+    file: File,
+}
+
+impl DirectoryLock {
+    pub const FILE_NAME: &'static str = ".openssl-build.lock";
+
+    pub fn acquire(directory: &Path, log_level: LogLevel) -> Result<Self, Box<dyn Error>> {
+        if !directory.exists() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let path = directory.join(Self::FILE_NAME);
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .open(&path)?;
+
+        if file.try_lock_exclusive().is_err() {
+            log_level.progress(format!(
+                "Waiting for another build to release {}",
+                path.display()
+            ));
+            file.lock_exclusive()?;
+        }
+
+        Ok(Self { file })
+    }
+}
+
+impl Drop for DirectoryLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LogLevel {
+    /// Only failures are reported
+    Silent,
+    /// Progress of each stage is reported, tool output goes to log files
+    Normal,
+    /// All output of Configure and make is streamed
+    Verbose,
+}
+
+impl Default for LogLevel {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+impl LogLevel {
+    /// Read from `OPENSSL_BUILD_LOG` (`silent`, `normal` or `verbose`), or `OPENSSL_BUILD_VERBOSE=1`
+    pub fn from_env() -> Option<Self> {
+        if let Ok(level) = std::env::var("OPENSSL_BUILD_LOG") {
+            return match level.to_lowercase().as_str() {
+                "silent" | "quiet" => Some(Self::Silent),
+                "normal" => Some(Self::Normal),
+                "verbose" => Some(Self::Verbose),
+                _ => None,
+            };
+        }
+        std::env::var("OPENSSL_BUILD_VERBOSE")
+            .ok()
+            .filter(|value| value == "1" || value == "true")
+            .map(|_| Self::Verbose)
+    }
+
+    /// Report progress on stderr, so that stdout stays free for json output
+    pub fn progress(self, message: impl AsRef<str>) {
+        if self >= Self::Normal {
+            eprintln!("{}", message.as_ref());
+        }
+    }
+
+    pub fn warning(self, message: impl AsRef<str>) {
+        if self >= Self::Normal {
+            eprintln!("warning: {}", message.as_ref());
+        }
+    }
+}
+
+/// How many lines of a failed step's log are printed
+const FAILURE_TAIL_LINES: usize = 50;
+
+/// Run the command with stdout and stderr written to the log file instead of the
+/// parent's output. When the command fails the tail of the log is printed
+pub fn run_logged(
+    command: &mut Command,
+    log_file: &Path,
+    log_level: LogLevel,
+) -> std::io::Result<ExitStatus> {
+    if let Some(directory) = log_file.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    let log = File::create(log_file)?;
+    command.stdout(log.try_clone()?).stderr(log);
+
+    let status = command.status()?;
+    if !status.success() {
+        print_tail(log_file, FAILURE_TAIL_LINES, log_level);
+    }
+    Ok(status)
+}
+
+pub fn print_tail(log_file: &Path, lines: usize, log_level: LogLevel) {
+    if log_level == LogLevel::Silent {
+        return;
+    }
+    let file = match File::open(log_file) {
+        Ok(file) => file,
+        Err(_) => return,
+    };
+    let all_lines = BufReader::new(file)
+        .lines()
+        .filter_map(|line| line.ok())
+        .collect::<Vec<String>>();
+    let start = all_lines.len().saturating_sub(lines);
+
+    log_level.progress(format!("Last lines of {}:", log_file.display()));
+    for line in &all_lines[start..] {
+        log_level.progress(line);
+    }
+}
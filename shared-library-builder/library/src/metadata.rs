@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::source::sha256_file;
+
+/// Written next to the compiled libraries
+pub const BUILD_METADATA_FILE: &str = "openssl-build.json";
+
+/// A compiled library with the SHA-256 of its contents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuiltFile {
+    pub file_name: String,
+    pub sha256: String,
+}
+
+impl BuiltFile {
+    pub fn new(file: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            file_name: file
+                .file_name()
+                .ok_or_else(|| format!("{} has no file name", file.display()))?
+                .to_string_lossy()
+                .to_string(),
+            sha256: sha256_file(file)?,
+        })
+    }
+}
+
+/// Describes what a compilation produced, so that tools consuming the libraries
+/// can tell exactly which OpenSSL they link against
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildMetadata {
+    pub version: String,
+    /// The commit of the sources, or the checksum of the source archive
+    pub source_revision: Option<String>,
+    pub target: String,
+    pub profile: String,
+    pub configure_arguments: Vec<String>,
+    pub libraries: Vec<BuiltFile>,
+}
+
+impl BuildMetadata {
+    pub fn read(file: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = std::fs::read_to_string(file)
+            .map_err(|error| format!("Could not read {}: {}", file.display(), error))?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn write(&self, file: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        std::fs::write(file, serde_json::to_string_pretty(self)?)?;
+        Ok(file.to_path_buf())
+    }
+}
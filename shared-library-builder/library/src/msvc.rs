@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use crate::targets::architecture;
+use crate::OpenSSLBuildError;
+
+/// Selects the Visual Studio installation used for MSVC builds. By default the compiler
+/// found by the `cc` crate is used, which is not necessarily the wanted one when
+/// several versions of Visual Studio are installed side by side.
+/// The environment of the selected `vcvarsall.bat` is passed to Configure and nmake
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MsvcToolchain {
+    visual_studio: Option<String>,
+    toolset: Option<String>,
+    vcvars: Option<PathBuf>,
+    /// The environments vcvarsall set up, by script. Shared between clones,
+    /// because every make command of a build needs the same environment
+    #[serde(skip)]
+    environments: Arc<Mutex<BTreeMap<String, Vec<(OsString, OsString)>>>>,
+}
+
+impl MsvcToolchain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The major version of Visual Studio (`16` for 2019, `17` for 2022),
+    /// or a vswhere version range such as `[16.0,17.0)`
+    pub fn with_visual_studio(mut self, version: impl Into<String>) -> Self {
+        self.visual_studio = Some(version.into());
+        self
+    }
+
+    /// The MSVC toolset passed to vcvarsall as `-vcvars_ver`, for example `14.29`
+    pub fn with_toolset(mut self, toolset: impl Into<String>) -> Self {
+        self.toolset = Some(toolset.into());
+        self
+    }
+
+    /// Use the given `vcvarsall.bat` instead of looking for an installation with vswhere
+    pub fn with_vcvars(mut self, vcvars: impl Into<PathBuf>) -> Self {
+        self.vcvars = Some(vcvars.into());
+        self
+    }
+
+    /// The environment set up by vcvarsall for compiling for the target.
+    /// vcvarsall only runs once for each target, later calls return the same environment
+    pub fn environment(
+        &self,
+        target: &str,
+    ) -> Result<Vec<(OsString, OsString)>, OpenSSLBuildError> {
+        let vcvars = self.vcvarsall()?;
+
+        let mut script = format!("\"{}\" {}", vcvars.display(), vcvars_architecture(target));
+        if let Some(toolset) = &self.toolset {
+            script.push_str(&format!(" -vcvars_ver={}", toolset));
+        }
+        script.push_str(" && set");
+
+        let mut environments = self.environments.lock().unwrap();
+        if let Some(environment) = environments.get(&script) {
+            return Ok(environment.clone());
+        }
+
+        let output = Command::new("cmd").arg("/C").arg(&script).output()?;
+        if !output.status.success() {
+            return Err(OpenSSLBuildError::EnvironmentMissing(format!(
+                "{} failed: {}",
+                vcvars.display(),
+                String::from_utf8_lossy(&output.stdout).trim()
+            )));
+        }
+
+        let environment: Vec<(OsString, OsString)> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        environments.insert(script, environment.clone());
+        Ok(environment)
+    }
+
+    fn vcvarsall(&self) -> Result<PathBuf, OpenSSLBuildError> {
+        if let Some(vcvars) = &self.vcvars {
+            return Ok(vcvars.clone());
+        }
+
+        let program_files = std::env::var("ProgramFiles(x86)")
+            .unwrap_or_else(|_| "C:\\Program Files (x86)".to_string());
+        let vswhere = PathBuf::from(program_files)
+            .join("Microsoft Visual Studio")
+            .join("Installer")
+            .join("vswhere.exe");
+        if !vswhere.exists() {
+            return Err(OpenSSLBuildError::ToolMissing(
+                vswhere.display().to_string(),
+            ));
+        }
+
+        let mut command = Command::new(&vswhere);
+        command
+            .arg("-latest")
+            .arg("-products")
+            .arg("*")
+            .arg("-requires")
+            .arg("Microsoft.VisualStudio.Component.VC.Tools.x86.x64")
+            .arg("-property")
+            .arg("installationPath");
+        if let Some(version) = &self.visual_studio {
+            command.arg("-version").arg(version_range(version));
+        }
+
+        let output = command.output()?;
+        let installation = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !output.status.success() || installation.is_empty() {
+            return Err(OpenSSLBuildError::ToolMissing(format!(
+                "Visual Studio {} with the C++ build tools",
+                self.visual_studio.as_deref().unwrap_or("")
+            )));
+        }
+
+        Ok(PathBuf::from(installation)
+            .join("VC")
+            .join("Auxiliary")
+            .join("Build")
+            .join("vcvarsall.bat"))
+    }
+}
+
+/// The directory of `clang-cl.exe`: the one on the `PATH`, or the LLVM toolchain
+/// that the Visual Studio installer adds to the installation in `VCINSTALLDIR`
+pub fn clang_cl_directory(vcinstalldir: Option<OsString>) -> Option<PathBuf> {
+    if let Ok(clang_cl) = which::which("clang-cl") {
+        return clang_cl.parent().map(|directory| directory.to_path_buf());
+    }
+    let host = if cfg!(target_arch = "aarch64") {
+        "ARM64"
+    } else {
+        "x64"
+    };
+    let directory = PathBuf::from(vcinstalldir?)
+        .join("Tools")
+        .join("Llvm")
+        .join(host)
+        .join("bin");
+    if directory.join("clang-cl.exe").exists() {
+        return Some(directory);
+    }
+    None
+}
+
+/// `17` becomes `[17.0,18.0)`, ranges are passed through
+fn version_range(version: &str) -> String {
+    match version.parse::<u32>() {
+        Ok(major) => format!("[{}.0,{}.0)", major, major + 1),
+        Err(_) => version.to_string(),
+    }
+}
+
+/// The vcvarsall argument for building on this host for the target
+fn vcvars_architecture(target: &str) -> &'static str {
+    let host_is_arm64 = cfg!(target_arch = "aarch64");
+    match (host_is_arm64, architecture(target)) {
+        (false, "x86_64") => "x64",
+        (false, "aarch64") => "x64_arm64",
+        (true, "x86_64") => "arm64_x64",
+        (true, _) => "arm64",
+        (false, _) => "x64",
+    }
+}
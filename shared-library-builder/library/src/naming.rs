@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// How the produced shared libraries are named. OpenSSL installs versioned names
+/// (`libssl.so.3`, `libssl.3.dylib`, `libssl-3-x64.dll`), FFI consumers that load the library
+/// by name often need a predictable one. Renamed copies are placed next to the installed files,
+/// which stay in place because libssl refers to libcrypto by its installed name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LibraryNaming {
+    /// Whatever `make install` produced
+    Installed,
+    /// `libcrypto.so`, `libcrypto.dylib`, `libcrypto.dll` and the same for libssl
+    Unversioned,
+    /// `lib<crypto>.so`, `lib<ssl>.so` and so on, with the given base names
+    Custom { crypto: String, ssl: String },
+}
+
+impl Default for LibraryNaming {
+    fn default() -> Self {
+        Self::Installed
+    }
+}
+
+impl LibraryNaming {
+    pub fn custom(crypto: impl Into<String>, ssl: impl Into<String>) -> Self {
+        Self::Custom {
+            crypto: crypto.into(),
+            ssl: ssl.into(),
+        }
+    }
+
+    /// The base name of the library, `crypto` or `ssl` unless custom names are used
+    pub fn base_name<'a>(&'a self, library: &'a str) -> &'a str {
+        match self {
+            Self::Custom { crypto, .. } if library == "crypto" => crypto.as_str(),
+            Self::Custom { ssl, .. } if library == "ssl" => ssl.as_str(),
+            _ => library,
+        }
+    }
+
+    /// Copy the installed library (`libssl.so.3`) to the file name this naming asks for.
+    /// Returns the renamed copy
+    pub fn apply(
+        &self,
+        library: &str,
+        installed: &Path,
+        extension: &str,
+    ) -> std::io::Result<Option<PathBuf>> {
+        if *self == Self::Installed {
+            return Ok(None);
+        }
+        let directory = match installed.parent() {
+            Some(directory) => directory,
+            None => return Ok(None),
+        };
+        let renamed = directory.join(format!("lib{}.{}", self.base_name(library), extension));
+        if renamed == installed {
+            return Ok(Some(renamed));
+        }
+        if renamed.symlink_metadata().is_ok() {
+            std::fs::remove_file(&renamed)?;
+        }
+        std::fs::copy(installed, &renamed)?;
+        Ok(Some(renamed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installed(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("libopenssl-naming-{}-{}", name, std::process::id()));
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+        std::fs::create_dir_all(&directory).unwrap();
+        let installed = directory.join("libssl.so.3");
+        std::fs::write(&installed, "libssl").unwrap();
+        installed
+    }
+
+    #[test]
+    fn base_names() {
+        let custom = LibraryNaming::custom("gtcrypto", "gtssl");
+        assert_eq!(custom.base_name("crypto"), "gtcrypto");
+        assert_eq!(custom.base_name("ssl"), "gtssl");
+        assert_eq!(LibraryNaming::Unversioned.base_name("ssl"), "ssl");
+    }
+
+    #[test]
+    fn installed_names_are_kept() {
+        let installed = installed("installed");
+        assert_eq!(
+            LibraryNaming::Installed
+                .apply("ssl", &installed, "so")
+                .unwrap(),
+            None
+        );
+        std::fs::remove_dir_all(installed.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn renamed_copies_are_placed_next_to_the_installed_library() {
+        let installed = installed("renamed");
+        let directory = installed.parent().unwrap().to_path_buf();
+        let unversioned = LibraryNaming::Unversioned
+            .apply("ssl", &installed, "so")
+            .unwrap();
+        assert_eq!(unversioned, Some(directory.join("libssl.so")));
+        let custom = LibraryNaming::custom("gtcrypto", "gtssl")
+            .apply("ssl", &installed, "so")
+            .unwrap();
+        assert_eq!(custom, Some(directory.join("libgtssl.so")));
+        assert_eq!(
+            std::fs::read_to_string(directory.join("libgtssl.so")).unwrap(),
+            "libssl"
+        );
+        assert!(installed.exists());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{RetryPolicy, SourceArchive};
+
+/// A NASM release that is downloaded into the build root when `nasm` is not installed.
+/// The archive is verified against the given SHA-256 before it is unpacked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NasmRelease {
+    version: String,
+    archive: SourceArchive,
+}
+
+impl NasmRelease {
+    pub fn new(
+        version: impl Into<String>,
+        url: impl Into<String>,
+        sha256: impl Into<String>,
+    ) -> Self {
+        Self {
+            version: version.into(),
+            archive: SourceArchive::new(url, sha256),
+        }
+    }
+
+    /// The official 64 bit Windows build from https://www.nasm.us, where `version` is for example `2.16.01`
+    pub fn win64(version: impl Into<String>, sha256: impl Into<String>) -> Self {
+        let version = version.into();
+        let url = format!(
+            "https://www.nasm.us/pub/nasm/releasebuilds/{version}/win64/nasm-{version}-win64.zip",
+            version = &version
+        );
+        Self::new(version, url, sha256)
+    }
+
+    pub fn version(&self) -> &str {
+        self.version.as_str()
+    }
+
+    /// Download, verify and unpack the release unless it is already there.
+    /// Returns the directory that contains `nasm.exe`
+    pub fn ensure(
+        &self,
+        build_root: &Path,
+        retry: &RetryPolicy,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let directory = build_root.join("nasm").join(&self.version);
+        if let Some(bin) = find_nasm(&directory) {
+            return Ok(bin);
+        }
+
+        let archive = self.archive.download(&build_root.join("nasm"), retry)?;
+        self.archive.verify(&archive)?;
+
+        std::fs::create_dir_all(&directory)?;
+        // the bsdtar shipped with Windows 10 and later also unpacks zip archives
+        let status = Command::new("tar")
+            .arg("-xf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&directory)
+            .status()?;
+        if !status.success() {
+            return Err(format!("Could not extract {}", archive.display()).into());
+        }
+
+        find_nasm(&directory)
+            .ok_or_else(|| format!("{} does not contain nasm.exe", archive.display()).into())
+    }
+}
+
+/// Release archives put the binaries into a `nasm-<version>` directory
+fn find_nasm(directory: &Path) -> Option<PathBuf> {
+    if directory.join("nasm.exe").exists() {
+        return Some(directory.to_path_buf());
+    }
+    std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.join("nasm.exe").exists())
+}
+
+/// Put the directory in front of the `PATH` the command would otherwise run with
+pub fn prepend_to_path(command: &mut Command, directory: &Path) {
+    let path = command
+        .get_envs()
+        .find(|(key, _)| key.to_string_lossy().eq_ignore_ascii_case("PATH"))
+        .and_then(|(_, value)| value.map(|value| value.to_os_string()))
+        .or_else(|| std::env::var_os("PATH"))
+        .unwrap_or_default();
+
+    let paths = std::iter::once(directory.to_path_buf()).chain(std::env::split_paths(&path));
+    let joined: OsString = std::env::join_paths(paths).unwrap_or(path);
+    command.env("PATH", joined);
+}
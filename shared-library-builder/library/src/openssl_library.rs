@@ -2,72 +2,2821 @@ use shared_library_builder::{
     CompiledLibraryName, GitLocation, Library, LibraryCompilationContext, LibraryDependencies,
     LibraryLocation, LibraryOptions, LibraryTarget,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use serde::{Deserialize, Serialize};
 use std::error::Error;
-use std::ffi::OsString;
-use std::path::PathBuf;
-use std::process::Command;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::android::AndroidNdk;
+#[cfg(feature = "bindgen")]
+use crate::bindings::generate_bindings;
+use crate::bindings::BINDINGS_FILE;
+use crate::checksums::{binaries_in, verify_checksums, write_checksums};
+use crate::cmake::{write_cmake_package, CMakeTarget};
+use crate::fingerprint::{
+    git_revision, is_configured_for, options_hash, BuildFingerprint, CONFIGURE_STAMP_FILE,
+    FINGERPRINT_FILE,
+};
+use crate::licenses::collect_licenses;
+use crate::lock::DirectoryLock;
+use crate::log::{run_logged, LogLevel};
+use crate::metadata::{BuildMetadata, BuiltFile, BUILD_METADATA_FILE};
+use crate::msvc::clang_cl_directory;
+use crate::nasm::prepend_to_path;
+use crate::package::{copy_directory, package_directory, package_headers, PackageManifest};
+use crate::prebuilt::{
+    latest_github_release, unpack, write_published_checksum, PrebuiltAsset, CHECKSUM_EXTENSION,
+    PREBUILT_STAMP_FILE, SIGNATURE_EXTENSION,
+};
+use crate::relocate::{
+    flatten_sonames, relocate_pkg_config, set_install_names, set_sonames, use_rpath_install_names,
+    RELOCATABLE_PKG_CONFIG_PREFIX,
+};
+use crate::report::{BuildReport, BuildStage, StageTimer};
+use crate::reproducible::{compare_builds, sort_archive_members, source_date_epoch};
+use crate::requirements::{
+    require_android_ndk, require_native_windows_perl, require_perl, require_tool,
+    MissingRequirement,
+};
+use crate::sbom::{SbomInputs, SBOM_FILE};
+use crate::strip::strip_library;
+use crate::symbols::{audit_symbols, REQUIRED_CRYPTO_SYMBOLS, REQUIRED_SSL_SYMBOLS};
+use crate::targets::{
+    architecture, configure_target, is_android, is_msvc, static_system_libraries, supports_ktls,
+};
+use crate::verify::verify_libraries;
+use crate::version::installed_version;
+use crate::{
+    Algorithms, BuildPlan, BuildTool, CompilerFlags, Engines, ExportedSymbols, GitCheckout,
+    GitFetchSettings, GitHubRelease, GitReference, HermeticEnvironment, InstallDirectories,
+    LibraryNaming, MsvcToolchain, NasmRelease, OpenSSLBuildError, OpenSSLFeature, OpenSSLProtocol,
+    OpenSSLVersion, OptimizationLevel, Pkcs11Dependency, Pkcs11Module, PlannedCommand,
+    PrebuiltBinaries, RetryPolicy, Sanitizer, SigningConfig, SourceArchive, SourcePatch,
+    ZlibDependency, ZlibLinkage,
+};
+
+/// Directory name shared by the crypto and ssl artefacts for sources and builds
+pub(crate) const OPENSSL_DIRECTORY_NAME: &str = "openssl";
+
+/// The binary version that stands for the newest feenkcom/libopenssl release
+const LATEST_RELEASE: &str = "latest";
+
+fn binary_release_location(tag: String) -> LibraryLocation {
+    LibraryLocation::Git(GitLocation::github("feenkcom", "libopenssl").tag(tag))
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 enum LibraryArtefact {
     Crypto,
     Ssl,
+    /// libcrypto and libssl from a single compilation
+    Both,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenSSLLibrary {
+    source_location: LibraryLocation,
+    git_checkout: GitCheckout,
+    version: OpenSSLVersion,
+    source_archive: Option<SourceArchive>,
+    local_source: Option<PathBuf>,
+    #[serde(skip)]
+    timer: StageTimer,
+    log_level: Option<LogLevel>,
+    git_fetch: GitFetchSettings,
+    patches: Vec<SourcePatch>,
+    configure_args: Vec<String>,
+    disabled_features: BTreeSet<OpenSSLFeature>,
+    disabled_protocols: BTreeSet<OpenSSLProtocol>,
+    algorithms: Algorithms,
+    engines: Engines,
+    api_level: Option<String>,
+    zlib: Option<ZlibDependency>,
+    pkcs11: Option<Pkcs11Dependency>,
+    directories: InstallDirectories,
+    tools: BTreeMap<BuildTool, String>,
+    sysroot: Option<PathBuf>,
+    perl: Option<PathBuf>,
+    make_tool: Option<PathBuf>,
+    clang_cl: bool,
+    lto: bool,
+    pic: Option<bool>,
+    hardened: bool,
+    optimization: Option<OptimizationLevel>,
+    debug_info: Option<bool>,
+    sanitizers: BTreeSet<Sanitizer>,
+    exported_symbols: Option<ExportedSymbols>,
+    hermetic: Option<HermeticEnvironment>,
+    flags: Vec<CompilerFlags>,
+    android_ndk: Option<PathBuf>,
+    android_api: Option<u8>,
+    msvc_toolchain: Option<MsvcToolchain>,
+    nasm: Option<NasmRelease>,
+    verification: bool,
+    upstream_tests: bool,
+    symbol_audit: bool,
+    required_symbols: Vec<String>,
+    rpath_install_names: bool,
+    sonames: Option<(String, String)>,
+    install_names: Option<(String, String)>,
+    flat_soname: bool,
+    pkg_config_prefix: Option<String>,
+    strip: bool,
+    signing: Option<SigningConfig>,
+    sbom: bool,
+    reproducible: bool,
+    naming: LibraryNaming,
+    dependencies: LibraryDependencies,
+    fips: bool,
+    fips_install: bool,
+    legacy_provider: bool,
+    ktls: bool,
+    quic: bool,
+    release_location: Option<LibraryLocation>,
+    binary_version: Option<String>,
+    /// The tag and release location `latest` resolved to, on first use
+    #[serde(skip)]
+    latest_release: OnceLock<Option<(String, LibraryLocation)>>,
+    prebuilt: Option<PrebuiltBinaries>,
+    prebuilt_release_assets: bool,
+    /// Applied to whichever prebuilt source is configured, never serialized
+    #[serde(skip)]
+    release_token: Option<String>,
+    release_public_key: Option<String>,
+    retry: Option<RetryPolicy>,
+    offline: bool,
+    options: LibraryOptions,
+    artefact: LibraryArtefact,
+}
+
+impl Default for OpenSSLLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OpenSSLLibrary {
-    source_location: LibraryLocation,
-    release_location: Option<LibraryLocation>,
-    options: LibraryOptions,
-    artefact: LibraryArtefact,
-}
+impl OpenSSLLibrary {
+    pub fn new() -> Self {
+        let version = OpenSSLVersion::default();
+        Self {
+            source_location: version.source_location(),
+            git_checkout: version.git_checkout(),
+            version,
+            source_archive: None,
+            local_source: None,
+            timer: Default::default(),
+            log_level: None,
+            git_fetch: Default::default(),
+            patches: vec![],
+            configure_args: vec![],
+            disabled_features: Default::default(),
+            disabled_protocols: Default::default(),
+            algorithms: Default::default(),
+            engines: Default::default(),
+            api_level: None,
+            zlib: None,
+            pkcs11: None,
+            directories: Default::default(),
+            tools: Default::default(),
+            sysroot: None,
+            perl: None,
+            make_tool: None,
+            clang_cl: false,
+            lto: false,
+            pic: None,
+            hardened: false,
+            optimization: None,
+            debug_info: None,
+            sanitizers: Default::default(),
+            exported_symbols: None,
+            hermetic: None,
+            flags: vec![],
+            android_ndk: None,
+            android_api: None,
+            msvc_toolchain: None,
+            nasm: None,
+            verification: false,
+            upstream_tests: false,
+            symbol_audit: false,
+            required_symbols: vec![],
+            rpath_install_names: false,
+            sonames: None,
+            install_names: None,
+            flat_soname: false,
+            pkg_config_prefix: Some(RELOCATABLE_PKG_CONFIG_PREFIX.to_string()),
+            strip: false,
+            signing: None,
+            sbom: false,
+            reproducible: false,
+            naming: Default::default(),
+            dependencies: LibraryDependencies::new(),
+            fips: false,
+            fips_install: false,
+            legacy_provider: false,
+            ktls: false,
+            quic: false,
+            release_location: None,
+            binary_version: None,
+            latest_release: OnceLock::new(),
+            prebuilt: None,
+            prebuilt_release_assets: false,
+            release_token: None,
+            release_public_key: None,
+            retry: None,
+            offline: false,
+            options: Default::default(),
+            artefact: LibraryArtefact::Crypto,
+        }
+    }
+
+    pub fn be_ssl(mut self) -> Self {
+        self.artefact = LibraryArtefact::Ssl;
+        self
+    }
+
+    pub fn be_crypto(mut self) -> Self {
+        self.artefact = LibraryArtefact::Crypto;
+        self
+    }
+
+    /// Produce libcrypto and libssl at once, for consumers that always need the pair.
+    /// `compile` returns libcrypto, see [`OpenSSLLibrary::artefact_libraries`] for both
+    pub fn be_both(mut self) -> Self {
+        self.artefact = LibraryArtefact::Both;
+        self
+    }
+
+    pub fn with_version(mut self, version: OpenSSLVersion) -> Self {
+        self.source_location = version.source_location();
+        self.git_checkout = version.git_checkout();
+        self.version = version;
+        self
+    }
+
+    /// Build the newest stable upstream release, see [`OpenSSLVersion::latest`].
+    /// The resolved tag is recorded in the package manifest and the SBOM
+    pub fn with_latest_version(self) -> Result<Self, Box<dyn Error>> {
+        if self.is_offline() {
+            return Err(OpenSSLBuildError::UnsupportedConfiguration(
+                "The latest OpenSSL release can not be resolved in offline mode".to_string(),
+            )
+            .into());
+        }
+        let version = OpenSSLVersion::latest(&self.git_fetch, &self.retry_policy())?;
+        self.progress(format!(
+            "Resolved the latest OpenSSL release to {}",
+            version.name()
+        ));
+        Ok(self.with_version(version))
+    }
+
+    pub fn version(&self) -> &OpenSSLVersion {
+        &self.version
+    }
+
+    /// Fetch the sources from a GitHub fork instead of the default repository of the selected version.
+    /// Use together with [`OpenSSLLibrary::branch`], [`OpenSSLLibrary::tag`] or [`OpenSSLLibrary::commit`]
+    pub fn with_git_source(
+        mut self,
+        owner: impl Into<String>,
+        repository: impl Into<String>,
+    ) -> Self {
+        let owner = owner.into();
+        let repository = repository.into();
+        self.git_checkout = GitCheckout::github(&owner, &repository);
+        self.source_location = LibraryLocation::Git(GitLocation::github(owner, repository));
+        self
+    }
+
+    pub fn branch(self, branch: impl Into<String>) -> Self {
+        self.with_git_reference(GitReference::Branch(branch.into()))
+    }
+
+    pub fn tag(self, tag: impl Into<String>) -> Self {
+        self.with_git_reference(GitReference::Tag(tag.into()))
+    }
+
+    pub fn commit(self, commit: impl Into<String>) -> Self {
+        self.with_git_reference(GitReference::Commit(commit.into()))
+    }
+
+    fn with_git_reference(mut self, reference: GitReference) -> Self {
+        if let LibraryLocation::Git(location) = &self.source_location {
+            let location = location.clone();
+            self.source_location = LibraryLocation::Git(match &reference {
+                GitReference::Branch(branch) => location.branch(branch.clone()),
+                GitReference::Tag(tag) => location.tag(tag.clone()),
+                GitReference::Commit(commit) => location.commit(commit.clone()),
+            });
+            self.git_checkout = self.git_checkout.clone().with_reference(reference);
+        }
+        self
+    }
+
+    /// Fetch GitHub repositories through a mirror, see [`GitFetchSettings::with_mirror`]
+    pub fn with_git_mirror(mut self, mirror: impl Into<String>) -> Self {
+        self.git_fetch = self.git_fetch.with_mirror(mirror);
+        self
+    }
+
+    pub fn with_git_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.git_fetch = self.git_fetch.with_proxy(proxy);
+        self
+    }
+
+    /// Clone private OpenSSL forks with a token, see [`GitFetchSettings::with_token`]
+    pub fn with_git_token(mut self, token: impl Into<String>) -> Self {
+        self.git_fetch = self.git_fetch.with_token(token);
+        self
+    }
+
+    /// Download prebuilt archives from a private repository or server with a token,
+    /// see [`PrebuiltBinaries::with_token`]
+    pub fn with_release_token(mut self, token: impl Into<String>) -> Self {
+        self.release_token = Some(token.into());
+        self
+    }
+
+    /// Require minisign signatures of prebuilt archives made with the key,
+    /// see [`PrebuiltBinaries::with_public_key`]
+    pub fn with_release_public_key(mut self, public_key: impl Into<String>) -> Self {
+        self.release_public_key = Some(public_key.into());
+        self
+    }
+
+    /// Build from a source tarball instead of the git repository of the selected version.
+    /// The archive is verified against its SHA-256 before extraction. The version is taken
+    /// from the name of release archives such as `openssl-3.0.13.tar.gz`
+    pub fn with_source_archive(mut self, source_archive: SourceArchive) -> Self {
+        if let Some(version) = source_archive.openssl_version() {
+            self = self.with_version(version);
+        }
+        self.source_archive = Some(source_archive);
+        self
+    }
+
+    /// Build from an existing OpenSSL checkout on disk without fetching anything.
+    /// Configure runs out of tree, so the working tree itself is left untouched.
+    /// Patches are not applied to a local checkout
+    pub fn with_local_source(mut self, path: impl Into<PathBuf>) -> Self {
+        self.local_source = Some(path.into());
+        self
+    }
+
+    /// The local sources and patch files the build reads from outside of its directories
+    pub(crate) fn input_files(&self) -> Vec<PathBuf> {
+        self.local_source
+            .iter()
+            .cloned()
+            .chain(
+                self.patches
+                    .iter()
+                    .filter_map(|patch| patch.path().map(|path| path.to_path_buf())),
+            )
+            .collect()
+    }
+
+    /// Apply a unified diff to the sources before Configure runs. Patches are applied in the order they are added
+    pub fn with_patch(mut self, patch: impl Into<SourcePatch>) -> Self {
+        self.patches.push(patch.into());
+        self
+    }
+
+    /// Append an arbitrary argument to the Configure invocation, for example `no-comp`, `enable-md2` or `-DPURIFY`
+    pub fn with_configure_arg(mut self, arg: impl Into<String>) -> Self {
+        self.configure_args.push(arg.into());
+        self
+    }
+
+    pub fn with_configure_args(
+        mut self,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.configure_args
+            .extend(args.into_iter().map(|arg| arg.into()));
+        self
+    }
+
+    pub fn without_feature(mut self, feature: OpenSSLFeature) -> Self {
+        self.disabled_features.insert(feature);
+        self
+    }
+
+    pub fn without_deprecated(self) -> Self {
+        self.without_feature(OpenSSLFeature::Deprecated)
+    }
+
+    pub fn without_engine(self) -> Self {
+        self.without_feature(OpenSSLFeature::Engine)
+    }
+
+    pub fn without_comp(self) -> Self {
+        self.without_feature(OpenSSLFeature::Comp)
+    }
+
+    pub fn without_dso(self) -> Self {
+        self.without_feature(OpenSSLFeature::Dso)
+    }
+
+    /// Portable C-only build, which also removes the NASM requirement on Windows
+    pub fn without_asm(self) -> Self {
+        self.without_feature(OpenSSLFeature::Asm)
+    }
+
+    pub fn without_protocol(mut self, protocol: OpenSSLProtocol) -> Self {
+        self.disabled_protocols.insert(protocol);
+        self
+    }
+
+    /// Compile out SSL 3, TLS 1.0, TLS 1.1 and DTLS 1.0
+    pub fn without_legacy_protocols(mut self) -> Self {
+        self.disabled_protocols.extend(OpenSSLProtocol::legacy());
+        self
+    }
+
+    pub fn with_algorithms(mut self, algorithms: Algorithms) -> Self {
+        self.algorithms = algorithms;
+        self
+    }
+
+    /// Build a PKCS#11 engine (libp11) or provider (pkcs11-provider) against the installed
+    /// OpenSSL, so that the package can talk to smartcards and HSMs. It is built after OpenSSL,
+    /// because it links against libcrypto, and is shipped with the engines or providers.
+    /// Requires a shared build for the host
+    pub fn with_pkcs11(mut self, pkcs11: Pkcs11Dependency) -> Self {
+        self.pkcs11 = Some(pkcs11);
+        self
+    }
+
+    /// Fetch and build the PKCS#11 module into the install prefix
+    fn build_pkcs11(&self, context: &LibraryCompilationContext) -> Result<(), Box<dyn Error>> {
+        let pkcs11 = match &self.pkcs11 {
+            Some(pkcs11) => pkcs11,
+            None => return Ok(()),
+        };
+        let module = pkcs11.module();
+        let target = context.target().to_string();
+        if self.is_static()
+            || is_msvc(&target)
+            || target != LibraryTarget::for_current_host().to_string()
+        {
+            return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                "{} is only built against shared host builds of OpenSSL, not for {}",
+                module.name(),
+                target
+            ))
+            .into());
+        }
+        if module == Pkcs11Module::Pkcs11Provider && !self.version.is_v3() {
+            return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                "pkcs11-provider requires OpenSSL 3, but {} was selected",
+                self.version.name()
+            ))
+            .into());
+        }
+
+        let source_directory = pkcs11.source_directory(&context.sources_root());
+        if !source_directory.join(module.source_marker()).exists() {
+            if self.is_offline() && !pkcs11.source().is_vendored() {
+                return Err(format!(
+                    "Sources of {} are not available in {} and fetching them is disabled in offline mode",
+                    module.name(),
+                    source_directory.display()
+                )
+                .into());
+            }
+            self.timer.time(BuildStage::FetchSources, || {
+                pkcs11
+                    .source()
+                    .ensure_sources(&source_directory, &self.retry_policy())
+            })?;
+        }
+
+        let build_directory = self.build_directory(context).join(module.name());
+        if build_directory.exists() {
+            std::fs::remove_dir_all(&build_directory)?;
+        }
+        std::fs::create_dir_all(&build_directory)?;
+        let engines_directory = self.engines_directory(context);
+        std::fs::create_dir_all(&engines_directory)?;
+
+        for (step, mut command) in pkcs11.build_commands(
+            &source_directory,
+            &build_directory,
+            &self.native_library_prefix(context),
+            &self.library_directory(context).join("pkgconfig"),
+            &engines_directory,
+        ) {
+            let step = format!("{}-{}", module.name(), step);
+            let status = self.run_step(context, &step, &mut command)?;
+            if !status.success() {
+                return Err(OpenSSLBuildError::InstallFailed {
+                    library: module.name().to_string(),
+                    status,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Build or leave out the capi, afalg and padlock engines. Built engines are installed into
+    /// [`OpenSSLLibrary::engines_directory`] and shipped in the package
+    pub fn with_engines(mut self, engines: Engines) -> Self {
+        self.engines = engines;
+        self
+    }
+
+    /// Hide functions deprecated in the given API version (`1.1.0`, `3.0`, ...) from the installed headers
+    pub fn with_api_level(mut self, api_level: impl Into<String>) -> Self {
+        self.api_level = Some(api_level.into());
+        self.without_deprecated()
+    }
+
+    /// Enable zlib compression. The given zlib library is declared as a dependency,
+    /// so it is compiled before OpenSSL is configured against it
+    pub fn enable_zlib(mut self, zlib: impl Into<Box<dyn Library>>, linkage: ZlibLinkage) -> Self {
+        let zlib = ZlibDependency::new(zlib, linkage);
+        self.dependencies = self.dependencies.push(zlib.library().clone_library());
+        self.zlib = Some(zlib);
+        self
+    }
+
+    /// Defaults to the install prefix. Relative values are resolved against the prefix
+    pub fn with_openssldir(mut self, openssldir: impl Into<String>) -> Self {
+        self.directories = self.directories.with_openssldir(openssldir);
+        self
+    }
+
+    /// Relative values are resolved against the prefix and compiled in as the absolute
+    /// `ENGINESDIR` of the build machine, see [`OpenSSLLibrary::runtime_vars`] for relocated libraries
+    pub fn with_enginesdir(mut self, enginesdir: impl Into<String>) -> Self {
+        self.directories = self.directories.with_enginesdir(enginesdir);
+        self
+    }
+
+    /// Relative values are resolved against the prefix and compiled in as the absolute
+    /// `MODULESDIR` of the build machine, see [`OpenSSLLibrary::runtime_vars`] for relocated libraries
+    pub fn with_modulesdir(mut self, modulesdir: impl Into<String>) -> Self {
+        self.directories = self.directories.with_modulesdir(modulesdir);
+        self
+    }
+
+    /// Use a specific compiler or binutil, for example a pinned `clang-17` as [`BuildTool::Cc`],
+    /// instead of the one Configure picks for the target. Passed to Configure as `CC=...`,
+    /// so a cross compile prefix is still prepended to a plain program name.
+    /// Falls back to `OPENSSL_CC`, `OPENSSL_CXX`, `OPENSSL_AR`, `OPENSSL_RANLIB` and `OPENSSL_LD`
+    pub fn with_tool(mut self, tool: BuildTool, program: impl Into<String>) -> Self {
+        self.tools.insert(tool, program.into());
+        self
+    }
+
+    fn tools(&self) -> Vec<(BuildTool, String)> {
+        BuildTool::ALL
+            .iter()
+            .filter_map(|tool| {
+                self.tools
+                    .get(tool)
+                    .cloned()
+                    .or_else(|| std::env::var(tool.env_variable()).ok())
+                    .filter(|program| !program.is_empty())
+                    .map(|program| (*tool, program))
+            })
+            .collect()
+    }
+
+    /// Cross compile against the root filesystem of the target, for example of an embedded
+    /// Linux device: `--sysroot` is added to the compiler and linker flags, and the library
+    /// directories of the sysroot to the library search path. Falls back to `OPENSSL_SYSROOT`
+    pub fn with_sysroot(mut self, sysroot: impl Into<PathBuf>) -> Self {
+        self.sysroot = Some(sysroot.into());
+        self
+    }
+
+    fn sysroot(&self) -> Option<PathBuf> {
+        self.sysroot
+            .clone()
+            .or_else(|| std::env::var_os("OPENSSL_SYSROOT").map(PathBuf::from))
+    }
+
+    /// Add compiler and linker flags, possibly only for a profile or target
+    pub fn with_flags(mut self, flags: CompilerFlags) -> Self {
+        self.flags.push(flags);
+        self
+    }
+
+    /// Add compiler flags to every build, for example `-march=x86-64-v3`
+    pub fn with_cflags(self, flags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.with_flags(CompilerFlags::new().cflags(flags))
+    }
+
+    /// Add linker flags to every build
+    pub fn with_ldflags(self, flags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.with_flags(CompilerFlags::new().ldflags(flags))
+    }
+
+    /// Build with link-time optimization, `-flto`, for smaller and faster libraries.
+    /// Supported by the GCC and Clang targets, not by MSVC
+    pub fn enable_lto(mut self) -> Self {
+        self.lto = true;
+        self
+    }
+
+    /// Compile position independent code, `-fPIC`, so that the static libraries of a `no-shared`
+    /// build can be linked into a shared library. On by default for static builds of unix
+    /// targets; shared builds are always position independent
+    pub fn with_pic(mut self, pic: bool) -> Self {
+        self.pic = Some(pic);
+        self
+    }
+
+    fn uses_pic(&self, target: &str) -> bool {
+        self.pic
+            .unwrap_or_else(|| self.is_static() || self.hardened)
+            && !is_msvc(target)
+    }
+
+    /// Compile with the optimization level instead of the one the Configure target picks for
+    /// the profile, `-O3` for release and `-O0` for debug builds of most targets
+    pub fn with_optimization(mut self, optimization: OptimizationLevel) -> Self {
+        self.optimization = Some(optimization);
+        self
+    }
+
+    /// Include debug information (`-g`, `/Zi` for MSVC) or leave it out (`-g0`) whatever the
+    /// profile. MSVC targets always keep the debug information of their Configure target
+    pub fn with_debug_info(mut self, debug_info: bool) -> Self {
+        self.debug_info = Some(debug_info);
+        self
+    }
+
+    /// Optimize for size and leave out debug information, for mobile and embedded targets
+    pub fn optimized_for_size(self) -> Self {
+        self.with_optimization(OptimizationLevel::Size)
+            .with_debug_info(false)
+    }
+
+    /// Harden the libraries of unix targets: stack protectors, `_FORTIFY_SOURCE=2` for optimized
+    /// builds, position independent code and, for ELF targets, full RELRO and a non-executable stack
+    pub fn hardened(mut self) -> Self {
+        self.hardened = true;
+        self
+    }
+
+    /// Compiler and linker flags of [`OpenSSLLibrary::hardened`]
+    fn hardening_flags(
+        &self,
+        context: &LibraryCompilationContext,
+        cflags: &mut Vec<String>,
+        ldflags: &mut Vec<String>,
+    ) {
+        let target = context.target().to_string();
+        if !self.hardened || context.is_windows() {
+            return;
+        }
+        cflags.push("-fstack-protector-strong".to_string());
+        if !context.is_debug() {
+            // glibc warns about _FORTIFY_SOURCE without optimization
+            cflags.push("-D_FORTIFY_SOURCE=2".to_string());
+        }
+        if !target.contains("-apple-") {
+            // the Mach-O stack is never executable and ld64 has no -z options
+            cflags.push("-Wa,--noexecstack".to_string());
+            ldflags.extend(
+                ["-Wl,-z,relro", "-Wl,-z,now", "-Wl,-z,noexecstack"]
+                    .iter()
+                    .map(|flag| flag.to_string()),
+            );
+        }
+    }
+
+    /// Instrument the libraries with the sanitizer, for example to diagnose crashes at the
+    /// FFI boundary. Can be combined, such as [`Sanitizer::Address`] with [`Sanitizer::Undefined`].
+    /// Best used with a debug build; not supported for MSVC targets
+    pub fn with_sanitizer(mut self, sanitizer: Sanitizer) -> Self {
+        self.sanitizers.insert(sanitizer);
+        self
+    }
+
+    /// Only export the symbols matching the patterns from the shared libraries, with a version
+    /// script on Linux and Android and an exported symbols list on Apple targets
+    pub fn with_exported_symbols(mut self, exported_symbols: ExportedSymbols) -> Self {
+        self.exported_symbols = Some(exported_symbols);
+        self
+    }
+
+    /// Write the version scripts or exported symbols list before the libraries are linked
+    fn write_exported_symbols(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<(), OpenSSLBuildError> {
+        let exported_symbols = match &self.exported_symbols {
+            Some(exported_symbols) if !self.is_static() => exported_symbols,
+            _ => return Ok(()),
+        };
+        let target = context.target().to_string();
+        let build_directory = self.build_directory(context);
+        if target.contains("-apple-") {
+            exported_symbols.write_exported_symbols_list(&build_directory)?;
+        } else {
+            exported_symbols.write_version_scripts(&build_directory, self.version.is_v3())?;
+        }
+        Ok(())
+    }
+
+    /// The archiver and ranlib that understand the bitcode or GIMPLE of LTO objects, unless
+    /// set with [`OpenSSLLibrary::with_tool`]. Apple's archiver handles them already
+    fn lto_tools(&self, target: &str) -> Vec<(BuildTool, String)> {
+        if !self.lto || target.contains("-apple-") {
+            return vec![];
+        }
+        let tools = self.tools();
+        let is_clang = is_android(target)
+            || tools
+                .iter()
+                .any(|(tool, program)| *tool == BuildTool::Cc && program.contains("clang"));
+        let (ar, ranlib) = if is_clang {
+            ("llvm-ar", "llvm-ranlib")
+        } else {
+            // prefixed by Configure with the cross compile prefix, if any
+            ("gcc-ar", "gcc-ranlib")
+        };
+        [(BuildTool::Ar, ar), (BuildTool::Ranlib, ranlib)]
+            .iter()
+            .filter(|(wrapped, _)| !tools.iter().any(|(tool, _)| tool == wrapped))
+            .map(|(tool, program)| (*tool, program.to_string()))
+            .collect()
+    }
+
+    /// Compiler and linker flags added to the ones of the Configure target
+    fn compiler_flags(&self, context: &LibraryCompilationContext) -> (Vec<String>, Vec<String>) {
+        let target = context.target().to_string();
+        let mut cflags = vec![];
+        let mut ldflags = vec![];
+        if is_msvc(&target)
+            && self.clang_cl
+            && architecture(&target) != architecture(&LibraryTarget::for_current_host().to_string())
+        {
+            // clang-cl compiles for the host unless told otherwise
+            cflags.push(format!("--target={}", target));
+        }
+        if is_msvc(&target) && self.uses_jom() {
+            // the parallel cl processes of jom write to the same pdb
+            cflags.push("/FS".to_string());
+        }
+        if self.uses_pic(&target) {
+            cflags.push("-fPIC".to_string());
+        }
+        self.hardening_flags(context, &mut cflags, &mut ldflags);
+        if let Some(optimization) = self.optimization {
+            cflags.push(optimization.flag(is_msvc(&target)).to_string());
+        }
+        match self.debug_info {
+            Some(true) if is_msvc(&target) => cflags.push("/Zi".to_string()),
+            Some(true) => cflags.push("-g".to_string()),
+            Some(false) if !is_msvc(&target) => cflags.push("-g0".to_string()),
+            _ => {}
+        }
+        if self.lto && !is_msvc(&target) {
+            cflags.push("-flto".to_string());
+            ldflags.push("-flto".to_string());
+        }
+        if let Some(sysroot) = self.sysroot() {
+            let flag = format!("--sysroot={}", sysroot.display());
+            cflags.push(flag.clone());
+            ldflags.push(flag);
+            // Debian style sysroots keep the libraries in a directory per architecture
+            let multiarch = format!("{}-linux-gnu", architecture(&target));
+            for directory in &[
+                sysroot.join("lib"),
+                sysroot.join("usr").join("lib"),
+                sysroot.join("lib").join(&multiarch),
+                sysroot.join("usr").join("lib").join(&multiarch),
+            ] {
+                if directory.is_dir() {
+                    ldflags.push(format!("-L{}", directory.display()));
+                    ldflags.push(format!("-Wl,-rpath-link,{}", directory.display()));
+                }
+            }
+        }
+        // last, so that they take precedence
+        let profile = context.profile().to_string();
+        for flags in self
+            .flags
+            .iter()
+            .filter(|flags| flags.applies_to(&profile, &target))
+        {
+            cflags.extend(flags.get_cflags().iter().cloned());
+            ldflags.extend(flags.get_ldflags().iter().cloned());
+        }
+        (cflags, ldflags)
+    }
+
+    /// The perl that runs Configure, instead of the first one on the `PATH`.
+    /// Falls back to `OPENSSL_PERL`
+    pub fn with_perl(mut self, perl: impl Into<PathBuf>) -> Self {
+        self.perl = Some(perl.into());
+        self
+    }
+
+    fn perl(&self) -> PathBuf {
+        self.perl
+            .clone()
+            .or_else(|| std::env::var_os("OPENSSL_PERL").map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from("perl"))
+    }
+
+    /// Run Configure and make with only the variables of the hermetic environment
+    /// instead of the full environment of the builder
+    pub fn with_hermetic_environment(mut self, environment: HermeticEnvironment) -> Self {
+        self.hermetic = Some(environment);
+        self
+    }
+
+    /// A command of the build, with the hermetic environment if there is one
+    fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut command = Command::new(program);
+        if let Some(hermetic) = &self.hermetic {
+            hermetic.apply(&mut command);
+        }
+        command
+    }
+
+    /// The make program that builds the configured sources, instead of `make`
+    /// or the `nmake` of Visual Studio. For MSVC targets this can be `jom`, which builds
+    /// in parallel with the nmake makefiles. Falls back to `OPENSSL_MAKE`
+    pub fn with_make_tool(mut self, make_tool: impl Into<PathBuf>) -> Self {
+        self.make_tool = Some(make_tool.into());
+        self
+    }
+
+    fn make_tool(&self) -> Option<PathBuf> {
+        self.make_tool
+            .clone()
+            .or_else(|| std::env::var_os("OPENSSL_MAKE").map(PathBuf::from))
+    }
+
+    fn uses_jom(&self) -> bool {
+        self.make_tool()
+            .and_then(|make_tool| {
+                make_tool
+                    .file_stem()
+                    .map(|stem| stem.eq_ignore_ascii_case("jom"))
+            })
+            .unwrap_or(false)
+    }
+
+    /// The Android NDK to build with, instead of the one given by `ANDROID_NDK` or `NDK_HOME`
+    pub fn with_ndk(mut self, ndk: impl Into<PathBuf>) -> Self {
+        self.android_ndk = Some(ndk.into());
+        self
+    }
+
+    /// The minimum Android API level, instead of the one of the compilation context
+    pub fn with_android_api(mut self, api: u8) -> Self {
+        self.android_api = Some(api);
+        self
+    }
+
+    fn android_ndk(&self) -> Result<AndroidNdk, OpenSSLBuildError> {
+        match &self.android_ndk {
+            Some(ndk) => AndroidNdk::new(ndk),
+            None => AndroidNdk::from_env(),
+        }
+    }
+
+    fn android_api(&self, context: &LibraryCompilationContext) -> String {
+        self.android_api
+            .map(|api| api.to_string())
+            .unwrap_or_else(|| context.android_target_api().to_string())
+    }
+
+    /// Put the clang of the NDK on the `PATH` and tell Configure where the NDK is
+    fn configure_android(
+        &self,
+        context: &LibraryCompilationContext,
+        command: &mut Command,
+    ) -> Result<(), OpenSSLBuildError> {
+        let ndk = self.android_ndk()?;
+        ndk.check_target(&context.target().to_string(), &self.android_api(context))?;
+        prepend_to_path(command, &ndk.toolchain_bin());
+        // OpenSSL 1.1.1 looks for ANDROID_NDK_HOME, OpenSSL 3 for ANDROID_NDK_ROOT
+        command.env("ANDROID_NDK_HOME", ndk.root());
+        command.env("ANDROID_NDK_ROOT", ndk.root());
+        Ok(())
+    }
+
+    /// Pin the Visual Studio version, toolset or vcvarsall used for MSVC targets
+    pub fn with_msvc_toolchain(mut self, toolchain: MsvcToolchain) -> Self {
+        self.msvc_toolchain = Some(toolchain);
+        self
+    }
+
+    /// Download the given NASM release into the build root when `nasm` is not on the `PATH`,
+    /// instead of failing the requirements check of MSVC builds
+    pub fn with_nasm_download(mut self, nasm: NasmRelease) -> Self {
+        self.nasm = Some(nasm);
+        self
+    }
+
+    /// Compile MSVC targets with clang-cl instead of cl.exe, using the linker, headers and
+    /// libraries of Visual Studio. clang-cl is taken from the `PATH` or from the LLVM
+    /// component of the Visual Studio installation
+    pub fn with_clang_cl(mut self, clang_cl: bool) -> Self {
+        self.clang_cl = clang_cl;
+        self
+    }
+
+    /// Prepend the directory of clang-cl to the `PATH` of Configure or nmake
+    fn provide_clang_cl(
+        &self,
+        context: &LibraryCompilationContext,
+        command: &mut Command,
+    ) -> Result<(), OpenSSLBuildError> {
+        if !self.clang_cl || !is_msvc(&context.target().to_string()) {
+            return Ok(());
+        }
+        let vcinstalldir = command
+            .get_envs()
+            .find(|(key, _)| key.to_string_lossy().eq_ignore_ascii_case("VCINSTALLDIR"))
+            .and_then(|(_, value)| value.map(|value| value.to_os_string()))
+            .or_else(|| std::env::var_os("VCINSTALLDIR"));
+        let directory = clang_cl_directory(vcinstalldir).ok_or_else(|| {
+            OpenSSLBuildError::ToolMissing(
+                "clang-cl (install the C++ Clang tools of Visual Studio or LLVM)".to_string(),
+            )
+        })?;
+        prepend_to_path(command, &directory);
+        Ok(())
+    }
+
+    /// Prepend a downloaded NASM to the `PATH` of nmake, if one is needed
+    fn provide_nasm(
+        &self,
+        context: &LibraryCompilationContext,
+        command: &mut Command,
+    ) -> Result<(), OpenSSLBuildError> {
+        let nasm = match &self.nasm {
+            Some(nasm) => nasm,
+            None => return Ok(()),
+        };
+        if self.disabled_features.contains(&OpenSSLFeature::Asm) || which::which("nasm").is_ok() {
+            return Ok(());
+        }
+        let directory = nasm
+            .ensure(&context.build_root(), &self.retry_policy())
+            .map_err(|error| {
+                OpenSSLBuildError::ToolMissing(format!("nasm {} ({})", nasm.version(), error))
+            })?;
+        prepend_to_path(command, &directory);
+        Ok(())
+    }
+
+    /// Extra Configure arguments and environment that depend on the target
+    fn configure_target_toolchain(
+        &self,
+        context: &LibraryCompilationContext,
+        command: &mut Command,
+        locate_toolchains: bool,
+    ) -> Result<(), OpenSSLBuildError> {
+        let target = context.target().to_string();
+
+        if is_msvc(&target) {
+            if let Some(toolchain) = self.msvc_toolchain.as_ref().filter(|_| locate_toolchains) {
+                command.envs(toolchain.environment(&target)?);
+            }
+            if self.clang_cl {
+                if locate_toolchains {
+                    self.provide_clang_cl(context, command)?;
+                }
+                command.arg("CC=clang-cl");
+            }
+        }
+        Ok(())
+    }
+
+    /// Build for both x86_64 and arm64 macOS and merge the results into a universal (fat) library
+    /// with headers that work for either architecture
+    pub fn compile_universal(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        crate::universal::compile_universal(self, context)
+    }
+
+    /// Build for the Apple targets of the contexts and assemble `libcrypto.xcframework` and
+    /// `libssl.xcframework` with the headers and a module map, for Xcode projects
+    pub fn compile_xcframeworks(
+        &self,
+        contexts: &[LibraryCompilationContext],
+    ) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        crate::xcframework::compile_xcframeworks(self, contexts)
+    }
+
+    /// Build for the Android targets of the contexts and arrange the shared libraries
+    /// as `<destination>/<abi>/libcrypto.so`, ready to be used as `jniLibs` of an Android project
+    pub fn compile_jni_libs(
+        &self,
+        contexts: &[LibraryCompilationContext],
+        destination: &Path,
+    ) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        crate::jni_libs::compile_jni_libs(self, contexts, destination)
+    }
+
+    /// By default the output of Configure and make goes to log files under `<build directory>/logs`
+    /// and only the tail is shown on failure. Verbose output streams everything instead.
+    /// Falls back to [`LogLevel::from_env`]
+    pub fn with_log_level(mut self, log_level: LogLevel) -> Self {
+        self.log_level = Some(log_level);
+        self
+    }
+
+    pub fn with_verbose_output(self, verbose: bool) -> Self {
+        self.with_log_level(if verbose {
+            LogLevel::Verbose
+        } else {
+            LogLevel::Normal
+        })
+    }
+
+    pub fn log_level(&self) -> LogLevel {
+        self.log_level
+            .or_else(LogLevel::from_env)
+            .unwrap_or_default()
+    }
+
+    fn progress(&self, message: impl AsRef<str>) {
+        self.log_level().progress(message);
+    }
+
+    /// Report a skipped step or a fallback, it ends up in the [`BuildReport`]
+    fn warning(&self, message: impl AsRef<str>) {
+        self.timer.warn(message.as_ref());
+        self.log_level().warning(message);
+    }
+
+    pub fn log_directory(&self, context: &LibraryCompilationContext) -> PathBuf {
+        self.build_directory(context).join("logs")
+    }
+
+    fn run_step(
+        &self,
+        context: &LibraryCompilationContext,
+        step: &str,
+        command: &mut Command,
+    ) -> std::io::Result<ExitStatus> {
+        if self.log_level() == LogLevel::Verbose {
+            return command.status();
+        }
+        self.progress(format!("Running {} of {}", step, self.name()));
+        run_logged(
+            command,
+            &self.log_directory(context).join(format!("{}.log", step)),
+            self.log_level(),
+        )
+    }
+
+    /// Compile the library and report how long each stage took
+    pub fn compile_with_report(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<(PathBuf, BuildReport), Box<dyn Error>> {
+        self.timer.reset();
+        let start = Instant::now();
+        let library = self.compile(context)?;
+        Ok((library, self.timer.report(start.elapsed())))
+    }
+
+    /// The commands a build would run, with their arguments, environment and directories,
+    /// without running them. Nothing is fetched, configured or built
+    pub fn plan(&self, context: &LibraryCompilationContext) -> Result<BuildPlan, Box<dyn Error>> {
+        // the toolchains are only located and validated when building
+        let make = || self.build_make_command(context, false);
+        let mut commands = vec![PlannedCommand::new(
+            "configure",
+            &self.build_configure_command(context, false)?,
+        )];
+        commands.push(PlannedCommand::new("make", make()?.arg("build_sw")));
+        if self.upstream_tests
+            && context.target().to_string() == LibraryTarget::for_current_host().to_string()
+        {
+            commands.push(PlannedCommand::new("test", make()?.arg("test")));
+        }
+        commands.push(PlannedCommand::new("install", make()?.arg("install_sw")));
+        if self.fips {
+            commands.push(PlannedCommand::new(
+                "install_fips",
+                make()?.arg("install_fips"),
+            ));
+        }
+
+        Ok(BuildPlan {
+            library: self.name().to_string(),
+            target: context.target().to_string(),
+            source_directory: self.source_directory(context),
+            build_directory: self.build_directory(context),
+            install_prefix: self.native_library_prefix(context),
+            commands,
+        })
+    }
+
+    /// Load the built libcrypto and libssl after the build and check the version libcrypto reports.
+    /// Skipped for static and cross-compiled builds, which can not be loaded on the host
+    pub fn with_verification(mut self, verification: bool) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    /// On macOS identify the installed dylibs by `@rpath/<file name>` instead of the absolute
+    /// path on the build machine. Off by default, because executables linked against them then
+    /// need an rpath, for example `-Wl,-rpath,@loader_path`, to find them
+    pub fn with_rpath_install_names(mut self, rpath_install_names: bool) -> Self {
+        self.rpath_install_names = rpath_install_names;
+        self
+    }
+
+    /// On Linux give libssl and libcrypto unversioned sonames (`libssl.so` instead of `libssl.so.3`),
+    /// for loaders that look for the library by its plain name. Requires `patchelf`
+    pub fn with_flat_soname(mut self, flat_soname: bool) -> Self {
+        self.flat_soname = flat_soname;
+        self
+    }
+
+    /// On Linux give libcrypto and libssl explicit sonames, for example
+    /// `libgtcrypto.so.1` and `libgtssl.so.1`, so that several copies of OpenSSL can coexist in
+    /// one process. Copies of the libraries are installed under these names. Requires `patchelf`
+    pub fn with_sonames(mut self, crypto: impl Into<String>, ssl: impl Into<String>) -> Self {
+        self.sonames = Some((crypto.into(), ssl.into()));
+        self
+    }
+
+    /// On macOS give libcrypto and libssl explicit install names, for example
+    /// `@rpath/libgtcrypto.1.dylib`. Copies of the libraries are installed under their file names
+    pub fn with_install_names(mut self, crypto: impl Into<String>, ssl: impl Into<String>) -> Self {
+        self.install_names = Some((crypto.into(), ssl.into()));
+        self
+    }
+
+    /// The `prefix=` written into the installed pkg-config files instead of the absolute
+    /// install prefix. Defaults to `${pcfiledir}/../..`, so that the install prefix can be moved,
+    /// `None` keeps the files as OpenSSL installed them
+    pub fn with_pkg_config_prefix(mut self, prefix: Option<String>) -> Self {
+        self.pkg_config_prefix = prefix;
+        self
+    }
+
+    /// Strip the installed shared libraries and keep their debug information in
+    /// [`OpenSSLLibrary::debug_directory`]: dSYM bundles on macOS, `.debug` files on Linux
+    /// and the PDBs on Windows
+    pub fn with_stripped_symbols(mut self, strip: bool) -> Self {
+        self.strip = strip;
+        self
+    }
+
+    /// Sign the installed libcrypto and libssl with `codesign` on macOS and `signtool` on Windows,
+    /// after they were stripped
+    pub fn with_signing(mut self, signing: SigningConfig) -> Self {
+        self.signing = Some(signing);
+        self
+    }
+
+    pub fn debug_directory(&self, context: &LibraryCompilationContext) -> PathBuf {
+        self.build_directory(context).join("debug")
+    }
+
+    /// The separated debug information of the stripped libraries
+    pub fn debug_artifacts(&self, context: &LibraryCompilationContext) -> Vec<PathBuf> {
+        let mut artifacts = std::fs::read_dir(self.debug_directory(context))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .collect::<Vec<PathBuf>>()
+            })
+            .unwrap_or_default();
+        artifacts.sort();
+        artifacts
+    }
+
+    /// Also provide the shared libraries under unversioned or custom names
+    pub fn with_library_naming(mut self, naming: LibraryNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    fn shared_library_extension(&self, context: &LibraryCompilationContext) -> &'static str {
+        let target = context.target().to_string();
+        if context.is_windows() {
+            "dll"
+        } else if target.contains("-apple-") {
+            "dylib"
+        } else {
+            "so"
+        }
+    }
+
+    /// The installed (versioned) shared library, ignoring symlinks and renamed copies
+    fn installed_shared_library(
+        &self,
+        context: &LibraryCompilationContext,
+        library: &str,
+    ) -> Option<PathBuf> {
+        let extension = format!(".{}", self.shared_library_extension(context));
+        let directory = self
+            .compiled_library_directories(context)
+            .into_iter()
+            .next()?;
+        std::fs::read_dir(directory)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.symlink_metadata()
+                    .map(|metadata| metadata.file_type().is_file())
+                    .unwrap_or(false)
+            })
+            .filter(|path| {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                name.starts_with(&format!("lib{}", library)) && name.contains(&extension)
+            })
+            // versioned names are the longest
+            .max_by_key(|path| path.as_os_str().len())
+    }
+
+    /// Rewrite the install names or sonames of the installed shared libraries
+    fn post_process(&self, context: &LibraryCompilationContext) -> Result<(), OpenSSLBuildError> {
+        let target = context.target().to_string();
+        if self.is_static() {
+            if self.reproducible && !is_msvc(&target) {
+                let (crypto_library, ssl_library) = self.compiled_libraries(context);
+                for archive in &[crypto_library, ssl_library] {
+                    sort_archive_members(archive, &target)?;
+                }
+            }
+            return Ok(());
+        }
+        let lib_directory = self.library_directory(context);
+        if self.rpath_install_names && target.contains("-apple-darwin") {
+            use_rpath_install_names(&lib_directory)?;
+        }
+        if self.flat_soname && target.contains("-linux-") && !is_android(&target) {
+            flatten_sonames(&lib_directory)?;
+        }
+        if let Some((crypto, ssl)) = &self.sonames {
+            if !target.contains("-apple-") && !context.is_windows() {
+                set_sonames(&lib_directory, crypto, ssl)?;
+            }
+        }
+        if let Some((crypto, ssl)) = &self.install_names {
+            if target.contains("-apple-") {
+                set_install_names(&lib_directory, crypto, ssl)?;
+            }
+        }
+        if self.strip {
+            let (crypto_library, ssl_library) = self.compiled_libraries(context);
+            for library in &[crypto_library, ssl_library] {
+                strip_library(library, &target, &self.debug_directory(context))?;
+            }
+        }
+        if let Some(signing) = &self.signing {
+            let (crypto_library, ssl_library) = self.compiled_libraries(context);
+            for library in &[crypto_library, ssl_library] {
+                signing.sign(&library.canonicalize()?, &target)?;
+            }
+        }
+        for library in &["crypto", "ssl"] {
+            if let Some(installed) = self.installed_shared_library(context, library) {
+                self.naming
+                    .apply(library, &installed, self.shared_library_extension(context))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run OpenSSL's own test suite (`make test`) after building.
+    /// Cross-compiled builds are not tested, because the test programs can not run on the host
+    pub fn run_upstream_tests(mut self, upstream_tests: bool) -> Self {
+        self.upstream_tests = upstream_tests;
+        self
+    }
+
+    fn run_tests(&self, context: &LibraryCompilationContext) -> Result<(), OpenSSLBuildError> {
+        if !self.upstream_tests {
+            return Ok(());
+        }
+        if context.target().to_string() != LibraryTarget::for_current_host().to_string() {
+            self.warning(format!(
+                "Skipping the test suite of {}, because {} is cross-compiled",
+                self.name(),
+                context.target()
+            ));
+            return Ok(());
+        }
+
+        let mut test = self.make_command(context)?;
+        let status = self.timer.time(BuildStage::Test, || {
+            self.run_step(context, "test", test.arg("test"))
+        })?;
+        if !status.success() {
+            return Err(OpenSSLBuildError::TestsFailed {
+                library: self.name().to_string(),
+                status,
+            });
+        }
+        Ok(())
+    }
+
+    fn verify(&self, context: &LibraryCompilationContext) -> Result<(), OpenSSLBuildError> {
+        if !self.verification
+            || self.is_static()
+            || context.target().to_string() != LibraryTarget::for_current_host().to_string()
+        {
+            return Ok(());
+        }
+
+        let (crypto_library, ssl_library) = self.compiled_libraries(context);
+        let version_num =
+            verify_libraries(&crypto_library, &ssl_library, self.version.major_minor())?;
+        self.progress(format!(
+            "Verified {} (version {:#x})",
+            crypto_library.display(),
+            version_num
+        ));
+        Ok(())
+    }
+
+    /// After the build check that libcrypto and libssl export the functions every build
+    /// is expected to have, see [`REQUIRED_CRYPTO_SYMBOLS`] and [`REQUIRED_SSL_SYMBOLS`].
+    /// Catches builds where features were silently disabled by Configure
+    pub fn with_symbol_audit(mut self, symbol_audit: bool) -> Self {
+        self.symbol_audit = symbol_audit;
+        self
+    }
+
+    /// A function that libcrypto or libssl must export, for example `EVP_aes_256_gcm`.
+    /// Enables the symbol audit
+    pub fn with_required_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.required_symbols.push(symbol.into());
+        self.with_symbol_audit(true)
+    }
+
+    fn run_symbol_audit(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<(), OpenSSLBuildError> {
+        if !self.symbol_audit {
+            return Ok(());
+        }
+        let target = context.target().to_string();
+        if is_msvc(&target) && self.is_static() {
+            self.warning("Skipping the symbol audit, static MSVC libraries have no export table");
+            return Ok(());
+        }
+
+        let (crypto_library, ssl_library) = self.compiled_libraries(context);
+        let to_strings = |symbols: &[&str]| -> Vec<String> {
+            symbols.iter().map(|symbol| symbol.to_string()).collect()
+        };
+        audit_symbols(
+            &[crypto_library.as_path()],
+            &target,
+            &to_strings(REQUIRED_CRYPTO_SYMBOLS),
+        )?;
+        audit_symbols(
+            &[ssl_library.as_path()],
+            &target,
+            &to_strings(REQUIRED_SSL_SYMBOLS),
+        )?;
+        audit_symbols(
+            &[crypto_library.as_path(), ssl_library.as_path()],
+            &target,
+            &self.required_symbols,
+        )
+    }
+
+    /// The compiled libraries this artefact stands for
+    pub fn artefact_libraries(&self, context: &LibraryCompilationContext) -> Vec<PathBuf> {
+        let (crypto_library, ssl_library) = self.compiled_libraries(context);
+        match self.artefact {
+            LibraryArtefact::Crypto => vec![crypto_library],
+            LibraryArtefact::Ssl => vec![ssl_library],
+            LibraryArtefact::Both => vec![crypto_library, ssl_library],
+        }
+    }
+
+    /// The compiled libcrypto and libssl
+    pub fn compiled_libraries(&self, context: &LibraryCompilationContext) -> (PathBuf, PathBuf) {
+        let crypto = self.clone().be_crypto();
+        let ssl = self.clone().be_ssl();
+        (
+            self.compiled_library_named(crypto.name(), crypto.compiled_library_name(), context),
+            self.compiled_library_named(ssl.name(), ssl.compiled_library_name(), context),
+        )
+    }
+
+    /// Build and install the FIPS provider. Requires OpenSSL 3
+    pub fn enable_fips(mut self) -> Self {
+        self.fips = true;
+        self
+    }
+
+    /// Run `openssl fipsinstall` after the build to generate `fipsmodule.cnf` next to the provider.
+    /// Skipped with a warning when cross-compiling, since the built `openssl` can not run on the host
+    pub fn with_fips_install(mut self, fips_install: bool) -> Self {
+        self.fips_install = fips_install;
+        self
+    }
+
+    /// Build the legacy provider (RC4, MD4, ...) as a loadable module and ship it next to libcrypto.
+    /// Requires OpenSSL 3
+    pub fn with_legacy_provider(mut self) -> Self {
+        self.legacy_provider = true;
+        self
+    }
+
+    /// Build libssl with kernel TLS (`enable-ktls`), so that servers can hand the record layer
+    /// to the kernel and use `SSL_sendfile`. Requires OpenSSL 3, only applies to Linux targets
+    pub fn enable_ktls(mut self) -> Self {
+        self.ktls = true;
+        self
+    }
+
+    /// Build libssl with QUIC (`enable-quic`), for HTTP/3 stacks. Requires OpenSSL 3.2 or newer,
+    /// for example [`OpenSSLVersion::V3_2`]
+    pub fn enable_quic(mut self) -> Self {
+        self.quic = true;
+        self
+    }
+
+    pub fn with_release_location(mut self, release_location: Option<LibraryLocation>) -> Self {
+        self.release_location = release_location;
+        self
+    }
+
+    /// Use the binaries of a feenkcom/libopenssl release instead of compiling,
+    /// see [`crate::libopenssl`]. `latest` is resolved when the library is compiled
+    pub fn with_binary_version(mut self, binary_version: Option<impl Into<String>>) -> Self {
+        self.binary_version = binary_version.map(|version| version.into());
+        self.latest_release = OnceLock::new();
+        self.release_location = self
+            .binary_version
+            .clone()
+            .filter(|version| version != LATEST_RELEASE)
+            .map(binary_release_location);
+        self
+    }
+
+    /// The binary version with `latest` resolved to a tag
+    fn binary_version(&self) -> Option<&str> {
+        match self.binary_version.as_deref()? {
+            LATEST_RELEASE => self.latest_release().map(|(tag, _)| tag.as_str()),
+            version => Some(version),
+        }
+    }
+
+    /// Resolves `latest` once, with retries. Without network or without a release
+    /// the libraries are compiled from sources
+    fn latest_release(&self) -> Option<&(String, LibraryLocation)> {
+        self.latest_release
+            .get_or_init(|| {
+                if self.is_offline() {
+                    self.warning(
+                        "The latest libopenssl release is not resolved in offline mode, compiling from sources",
+                    );
+                    return None;
+                }
+                let resolved = self
+                    .retry_policy()
+                    .retry("Resolving the latest libopenssl release", || {
+                        latest_github_release("feenkcom", "libopenssl")
+                    });
+                match resolved {
+                    Ok(tag) => {
+                        self.progress(format!("Resolved the latest libopenssl release to {}", tag));
+                        Some((tag.clone(), binary_release_location(tag)))
+                    }
+                    Err(error) => {
+                        self.warning(format!(
+                            "Could not resolve the latest libopenssl release, compiling from sources: {}",
+                            error
+                        ));
+                        None
+                    }
+                }
+            })
+            .as_ref()
+    }
+
+    /// Also look for a prebuilt archive matching the Configure options among the assets of the
+    /// binary release, see [`OpenSSLLibrary::with_prebuilt_binaries`]. Off by default
+    pub fn with_prebuilt_release_assets(mut self) -> Self {
+        self.prebuilt_release_assets = true;
+        self
+    }
+
+    /// How often git clones and downloads are attempted, see [`RetryPolicy`]
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// The configured policy, or the default adjusted by `OPENSSL_NETWORK_ATTEMPTS`
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+            .clone()
+            .unwrap_or_else(RetryPolicy::from_env)
+            .with_log_level(self.log_level())
+    }
+
+    /// Before compiling, look for a prebuilt archive of the same version, target and
+    /// Configure options among the release assets and install it instead.
+    /// The build falls back to compiling from sources when there is no matching asset
+    pub fn with_prebuilt_binaries(mut self, prebuilt: Option<PrebuiltBinaries>) -> Self {
+        self.prebuilt = prebuilt;
+        self
+    }
+
+    /// Identifies the Configure options of the build independently of the machine,
+    /// see [`options_hash`]
+    pub fn options_hash(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<String, Box<dyn Error>> {
+        let configure = self.configure_command(context)?;
+        let build_directory = self.build_directory(context);
+        let source_directory = self.source_directory(context);
+        Ok(options_hash(
+            &configure,
+            &context.target().to_string(),
+            &context.profile().to_string(),
+            &[build_directory.as_path(), source_directory.as_path()],
+        ))
+    }
+
+    /// Download prebuilt archives from an HTTP server instead of a GitHub release, see
+    /// [`PrebuiltBinaries::template`] for the placeholders of the url template
+    pub fn with_release_url_template(self, url_template: impl Into<String>) -> Self {
+        self.with_prebuilt_binaries(Some(PrebuiltBinaries::template(url_template)))
+    }
+
+    /// The release asset for this build, `openssl-<version>-<target>-<options hash>.tar.gz`
+    /// or `.zip` for Windows
+    pub fn prebuilt_asset(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<PrebuiltAsset, Box<dyn Error>> {
+        Ok(PrebuiltAsset {
+            version: self.version.name(),
+            target: context.target().to_string(),
+            options_hash: self.options_hash(context)?,
+            zip: context.is_windows(),
+        })
+    }
+
+    /// The configured prebuilt source with the release token and public key of the library,
+    /// regardless of the order in which they were set
+    fn prebuilt(&self) -> Option<PrebuiltBinaries> {
+        let mut prebuilt = match &self.prebuilt {
+            Some(prebuilt) => prebuilt.clone(),
+            None if self.prebuilt_release_assets => {
+                PrebuiltBinaries::github("feenkcom", "libopenssl", self.binary_version()?)
+            }
+            None => return None,
+        };
+        if let Some(token) = &self.release_token {
+            prebuilt = prebuilt.with_token(token.clone());
+        }
+        if let Some(public_key) = &self.release_public_key {
+            prebuilt = prebuilt.with_public_key(public_key.clone());
+        }
+        Some(prebuilt)
+    }
+
+    fn may_use_prebuilt(&self) -> bool {
+        (self.prebuilt.is_some() || (self.prebuilt_release_assets && self.binary_version.is_some()))
+            && !self.is_offline()
+            && self.local_source.is_none()
+            && self.patches.is_empty()
+    }
+
+    /// Install a matching prebuilt archive if there is one. Problems with the download
+    /// are reported and lead to a build from sources. Runs once per build, from
+    /// `ensure_sources`, under the lock of the build directory
+    fn use_prebuilt(&self, context: &LibraryCompilationContext) -> bool {
+        if !self.may_use_prebuilt() {
+            return false;
+        }
+        let installed = DirectoryLock::acquire(&self.build_directory(context), self.log_level())
+            .and_then(|_lock| self.install_prebuilt(context));
+        match installed {
+            Ok(installed) => installed,
+            Err(error) => {
+                self.warning(format!(
+                    "Could not use a prebuilt {}, building from sources: {}",
+                    self.name(),
+                    error
+                ));
+                false
+            }
+        }
+    }
+
+    /// The stamp of the installed prebuilt archive names the asset and all libraries exist
+    fn is_prebuilt_installed(&self, context: &LibraryCompilationContext, asset_name: &str) -> bool {
+        std::fs::read_to_string(self.build_directory(context).join(PREBUILT_STAMP_FILE))
+            .map(|stored| stored.trim() == asset_name)
+            .unwrap_or(false)
+            && self
+                .artefact_libraries(context)
+                .iter()
+                .all(|library| library.exists())
+    }
+
+    fn install_prebuilt(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<bool, Box<dyn Error>> {
+        let prebuilt = match self.prebuilt() {
+            Some(prebuilt) => prebuilt,
+            None => return Ok(false),
+        };
+        let asset = self.prebuilt_asset(context)?;
+        let asset_name = asset.name();
+        let build_directory = self.build_directory(context);
+        let stamp = build_directory.join(PREBUILT_STAMP_FILE);
+        if self.is_prebuilt_installed(context, &asset_name) {
+            return Ok(true);
+        }
+
+        let downloads = build_directory.join("prebuilt");
+        let archive =
+            match prebuilt.download(&asset, &downloads, &self.retry_policy(), self.log_level())? {
+                Some(archive) => archive,
+                None => {
+                    self.progress(format!(
+                        "No prebuilt {} at {}, building from sources",
+                        self.name(),
+                        prebuilt.url(&asset)
+                    ));
+                    return Ok(false);
+                }
+            };
+        self.progress(format!("Using prebuilt {}", prebuilt.url(&asset)));
+
+        let unpacked = downloads.join("unpacked");
+        unpack(&archive, &unpacked)?;
+        let root = unpacked.join(asset.stem());
+        verify_checksums(&root)?;
+
+        let packaged_lib = root.join("lib");
+        // archives packaged for one artefact before both libraries were packaged
+        if let Some(missing) = self
+            .artefact_libraries(context)
+            .iter()
+            .filter_map(|library| library.file_name())
+            .find(|file_name| !packaged_lib.join(file_name).is_file())
+        {
+            self.progress(format!(
+                "{} does not contain {}, building from sources",
+                asset_name,
+                missing.to_string_lossy()
+            ));
+            return Ok(false);
+        }
+
+        let prefix = self.native_library_prefix(context);
+        let library_directory = self
+            .compiled_library_directories(context)
+            .into_iter()
+            .next()
+            .ok_or_else(|| OpenSSLBuildError::UnsupportedTarget(context.target().to_string()))?;
+        copy_directory(&root.join("include"), &prefix.join("include"))?;
+        std::fs::create_dir_all(&library_directory)?;
+        for entry in std::fs::read_dir(&packaged_lib)? {
+            let entry = entry?;
+            if entry.path().is_file() {
+                std::fs::copy(entry.path(), library_directory.join(entry.file_name()))?;
+            }
+        }
+        if packaged_lib.join("ossl-modules").is_dir() {
+            copy_directory(
+                &packaged_lib.join("ossl-modules"),
+                &self.modules_directory(context),
+            )?;
+        }
+        if packaged_lib.join(self.engines_directory_name()).is_dir() {
+            copy_directory(
+                &packaged_lib.join(self.engines_directory_name()),
+                &self.engines_directory(context),
+            )?;
+        }
+        if packaged_lib.join("pkgconfig").is_dir() {
+            copy_directory(
+                &packaged_lib.join("pkgconfig"),
+                &self.library_directory(context).join("pkgconfig"),
+            )?;
+        }
+        self.write_cmake_package(context)?;
+        self.write_build_metadata(context)?;
+        std::fs::write(&stamp, &asset_name)?;
+        Ok(true)
+    }
+
+    /// Configure and make run in a directory namespaced by version and target,
+    /// so that several OpenSSL builds can share one build root.
+    /// The crypto and ssl artefacts share the directory, so one `make install_sw` produces both
+    pub fn build_directory(&self, context: &LibraryCompilationContext) -> PathBuf {
+        context
+            .build_root()
+            .join(OPENSSL_DIRECTORY_NAME)
+            .join(self.version.name())
+            .join(context.target().to_string())
+    }
+
+    /// Remove the build directory of the version and target, including the install prefix,
+    /// the debug information and the packages. With `sources` the source checkout or archive
+    /// is removed as well, local source trees are never touched. Must not run while building.
+    /// Returns the removed directories
+    pub fn clean(
+        &self,
+        context: &LibraryCompilationContext,
+        sources: bool,
+    ) -> std::io::Result<Vec<PathBuf>> {
+        let mut directories = vec![self.build_directory(context)];
+        if sources && self.local_source.is_none() {
+            directories.push(self.source_directory(context));
+        }
+
+        let mut removed = vec![];
+        for directory in directories {
+            if directory.exists() {
+                self.progress(format!("Removing {}", directory.display()));
+                std::fs::remove_dir_all(&directory)?;
+                removed.push(directory);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Copy the license and notice files of OpenSSL and of its dependencies into the destination,
+    /// so that redistributors of the libraries ship them together
+    pub fn copy_licenses(
+        &self,
+        context: &LibraryCompilationContext,
+        destination: &Path,
+    ) -> std::io::Result<Vec<PathBuf>> {
+        let mut licenses =
+            collect_licenses("openssl", &self.source_directory(context), destination)?;
+        if let Some(zlib) = &self.zlib {
+            licenses.extend(collect_licenses(
+                zlib.library().name(),
+                &zlib.library().source_directory(context),
+                destination,
+            )?);
+        }
+        if let Some(pkcs11) = &self.pkcs11 {
+            licenses.extend(collect_licenses(
+                pkcs11.module().name(),
+                &pkcs11.source_directory(&context.sources_root()),
+                destination,
+            )?);
+        }
+        Ok(licenses)
+    }
+
+    /// Record the SHA-256 of every installed binary in `SHA256SUMS` in the install prefix
+    fn write_checksums(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let prefix = self.native_library_prefix(context);
+        let mut directories = self.compiled_library_directories(context);
+        directories.push(prefix.join("bin"));
+        write_checksums(&prefix, &binaries_in(&directories))
+    }
+
+    /// The environment variables with which the `openssl-sys` crate links against this build
+    /// instead of a system OpenSSL: `OPENSSL_DIR`, `OPENSSL_LIB_DIR`, `OPENSSL_INCLUDE_DIR`
+    /// and `OPENSSL_STATIC`
+    pub fn openssl_sys_vars(&self, context: &LibraryCompilationContext) -> Vec<(String, OsString)> {
+        let prefix = self.native_library_prefix(context);
+        vec![
+            ("OPENSSL_DIR".to_string(), prefix.clone().into()),
+            (
+                "OPENSSL_LIB_DIR".to_string(),
+                self.library_directory(context).into(),
+            ),
+            (
+                "OPENSSL_INCLUDE_DIR".to_string(),
+                prefix.join("include").into(),
+            ),
+            (
+                "OPENSSL_STATIC".to_string(),
+                if self.is_static() { "1" } else { "0" }.into(),
+            ),
+        ]
+    }
+
+    /// Set [`OpenSSLLibrary::openssl_sys_vars`] for a command, so that a `cargo` it runs builds
+    /// `openssl-sys` against this build. The environment of the current process is left alone
+    pub fn configure_openssl_sys(
+        &self,
+        context: &LibraryCompilationContext,
+        command: &mut Command,
+    ) {
+        command.envs(self.openssl_sys_vars(context));
+    }
+
+    /// Generate Rust FFI bindings for the installed headers of the compiled library into
+    /// `bindings.rs` of the install prefix, which is also added to the package.
+    /// Requires the `bindgen` feature and libclang
+    #[cfg(feature = "bindgen")]
+    pub fn generate_bindings(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let prefix = self.native_library_prefix(context);
+        generate_bindings(
+            &prefix.join("include"),
+            &context.target().to_string(),
+            &prefix.join(BINDINGS_FILE),
+        )
+    }
+
+    /// `openssl-build.json` next to the compiled libraries
+    pub fn build_metadata_file(&self, context: &LibraryCompilationContext) -> PathBuf {
+        self.compiled_library_directories(context)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| self.library_directory(context))
+            .join(BUILD_METADATA_FILE)
+    }
+
+    /// What the last compilation for the context produced, read from `openssl-build.json`
+    pub fn build_metadata(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<BuildMetadata, Box<dyn Error>> {
+        BuildMetadata::read(&self.build_metadata_file(context))
+    }
+
+    fn write_build_metadata(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let (crypto_library, ssl_library) = self.compiled_libraries(context);
+        BuildMetadata {
+            version: self.version.name(),
+            source_revision: self.source_revision(context),
+            target: context.target().to_string(),
+            profile: context.profile().to_string(),
+            configure_arguments: self
+                .configure_command(context)?
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+            libraries: vec![
+                BuiltFile::new(&crypto_library)?,
+                BuiltFile::new(&ssl_library)?,
+            ],
+        }
+        .write(&self.build_metadata_file(context))
+    }
+
+    /// Write a CMake config-file package into `lib/cmake/OpenSSL` of the install prefix
+    /// for `find_package(OpenSSL CONFIG)`
+    fn write_cmake_package(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<(), Box<dyn Error>> {
+        let target = context.target().to_string();
+        let prefix = self.native_library_prefix(context);
+        let version = match installed_version(&prefix.join("include")) {
+            Some(version) => version,
+            None => {
+                self.warning(format!(
+                    "Not writing a CMake package, the version of {} is unknown",
+                    self.version.name()
+                ));
+                return Ok(());
+            }
+        };
+        let (crypto_library, ssl_library) = self.compiled_libraries(context);
+        let import_library = |name: &str| {
+            if self.is_static() {
+                None
+            } else if is_msvc(&target) {
+                Some(prefix.join("lib").join(format!("lib{}.lib", name)))
+            } else {
+                None
+            }
+        };
+        let crypto_link_libraries = if self.is_static() {
+            static_system_libraries(&target)
+                .into_iter()
+                .map(|library| library.to_string())
+                .collect()
+        } else {
+            vec![]
+        };
+        write_cmake_package(
+            &prefix,
+            &version,
+            self.is_static(),
+            &[
+                CMakeTarget {
+                    name: "Crypto".to_string(),
+                    location: crypto_library,
+                    import_library: import_library("crypto"),
+                    link_libraries: crypto_link_libraries,
+                },
+                CMakeTarget {
+                    name: "SSL".to_string(),
+                    location: ssl_library,
+                    import_library: import_library("ssl"),
+                    link_libraries: vec!["OpenSSL::Crypto".to_string()],
+                },
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Write a CycloneDX software bill of materials (`openssl.cdx.json`) into the install prefix,
+    /// recording the version, source revision, Configure arguments, patches and compiler
+    pub fn with_sbom(mut self, sbom: bool) -> Self {
+        self.sbom = sbom;
+        self
+    }
+
+    fn write_sbom(&self, context: &LibraryCompilationContext) -> Result<PathBuf, Box<dyn Error>> {
+        let configure = self.configure_command(context)?;
+        let source = match (&self.local_source, &self.source_archive) {
+            (Some(local_source), _) => local_source.display().to_string(),
+            (None, Some(source_archive)) => source_archive.url().to_string(),
+            (None, None) => serde_json::to_string(&self.source_location)?,
+        };
+        let compiler = configure
+            .get_envs()
+            .find(|(key, _)| *key == "CC")
+            .and_then(|(_, value)| value)
+            .map(|value| value.to_string_lossy().to_string())
+            .unwrap_or_else(|| "cc".to_string());
+
+        let sbom = self.native_library_prefix(context).join(SBOM_FILE);
+        SbomInputs {
+            version: self.version.name(),
+            license: if self.version.is_v3() {
+                "Apache-2.0"
+            } else {
+                "OpenSSL"
+            }
+            .to_string(),
+            target: context.target().to_string(),
+            source,
+            source_revision: self.source_revision(context),
+            configure_arguments: configure
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+            patches: self.patches.iter().map(|patch| patch.name()).collect(),
+            compiler: format!("{} ({})", compiler, self.compiler(context)?),
+            dependencies: self
+                .zlib
+                .iter()
+                .map(|zlib| (zlib.library().name().to_string(), None))
+                .chain(
+                    self.pkcs11
+                        .iter()
+                        .map(|pkcs11| (pkcs11.module().name().to_string(), None)),
+                )
+                .collect(),
+        }
+        .write(&sbom)?;
+        Ok(sbom)
+    }
+
+    /// Build bit-identical libraries from the same sources and options: timestamps come from
+    /// `SOURCE_DATE_EPOCH` (the commit time of the sources by default), build paths are not
+    /// embedded and archives are written deterministically.
+    /// See [`OpenSSLLibrary::verify_reproducible`]
+    pub fn with_reproducible_build(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    fn reproducible_environment(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Vec<(String, String)> {
+        if !self.reproducible {
+            return vec![];
+        }
+        let target = context.target().to_string();
+        let mut environment = vec![
+            (
+                "SOURCE_DATE_EPOCH".to_string(),
+                source_date_epoch(&self.source_directory(context)),
+            ),
+            // Configure walks perl hashes to list the sources and objects of the makefiles,
+            // a fixed hash seed lists them in the same order on every run
+            ("PERL_HASH_SEED".to_string(), "0".to_string()),
+            ("PERL_PERTURB_KEYS".to_string(), "0".to_string()),
+            // sorted file lists do not depend on the locale
+            ("LC_ALL".to_string(), "C".to_string()),
+        ];
+        if target.contains("-apple-") {
+            environment.push(("ZERO_AR_DATE".to_string(), "1".to_string()));
+        } else if !is_msvc(&target) {
+            environment.push(("ARFLAGS".to_string(), "rD".to_string()));
+        }
+        environment
+    }
+
+    /// Build twice in the same build directory and check that the libraries are bit-identical.
+    /// The libraries of the first build are kept aside in `<build root>/reproducibility-check`
+    /// and the build directory is cleaned before the second build, so that both builds see
+    /// the same paths
+    pub fn verify_reproducible(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<(), Box<dyn Error>> {
+        let library = self.clone().with_reproducible_build(true);
+        library.compile(context)?;
+
+        let first_build = context
+            .build_root()
+            .join("reproducibility-check")
+            .join(context.target().to_string());
+        if first_build.exists() {
+            std::fs::remove_dir_all(&first_build)?;
+        }
+        std::fs::create_dir_all(&first_build)?;
+        let mut first_libraries = vec![];
+        for compiled in library.artefact_libraries(context) {
+            let file_name = compiled
+                .file_name()
+                .ok_or_else(|| format!("{} has no file name", compiled.display()))?;
+            let copy = first_build.join(file_name);
+            std::fs::copy(&compiled, &copy)?;
+            first_libraries.push(copy);
+        }
+
+        library.clean(context, false)?;
+        library.compile(context)?;
+        compare_builds(&first_libraries, &library.artefact_libraries(context))
+    }
+
+    /// Where distributable archives of the build are written
+    pub fn package_directory(&self, context: &LibraryCompilationContext) -> PathBuf {
+        self.build_directory(context).join("package")
+    }
+
+    /// Bundle the installed headers into `openssl-<version>-<target>-headers.tar.gz`,
+    /// so that bindgen or C builds can use exactly the headers of a released binary
+    pub fn package_headers(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        package_headers(
+            &self.native_library_prefix(context),
+            &self.package_directory(context).join(format!(
+                "openssl-{}-{}-headers.tar.gz",
+                self.version.name(),
+                context.target()
+            )),
+        )
+    }
+
+    /// Bundle libcrypto and libssl, the headers, pkg-config files and a `manifest.json`
+    /// into `openssl-<version>-<target>-<options hash>.tar.gz` (`.zip` for Windows), the layout
+    /// that is uploaded to GitHub releases and installed by
+    /// [`OpenSSLLibrary::with_prebuilt_binaries`]. Both libraries are packaged whichever
+    /// artefact packages them, so that the archive serves either. Expects the library to be compiled
+    pub fn package(&self, context: &LibraryCompilationContext) -> Result<PathBuf, Box<dyn Error>> {
+        let prefix = self.native_library_prefix(context);
+        let staging = self
+            .package_directory(context)
+            .join(self.prebuilt_asset(context)?.stem());
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)?;
+        }
+
+        let (crypto_library, ssl_library) = self.compiled_libraries(context);
+        let libraries = vec![crypto_library, ssl_library];
+        if let Some(missing) = libraries.iter().find(|library| !library.exists()) {
+            return Err(format!(
+                "{} does not exist, compile the library before packaging it",
+                missing.display()
+            )
+            .into());
+        }
+        let staging_lib = staging.join("lib");
+        std::fs::create_dir_all(&staging_lib)?;
+        for library in &libraries {
+            let file_name = library
+                .file_name()
+                .ok_or_else(|| format!("{} has no file name", library.display()))?;
+            std::fs::copy(library, staging_lib.join(file_name))?;
+        }
+        let modules = self.provider_artifacts(context);
+        if !modules.is_empty() {
+            let staging_modules = staging_lib.join("ossl-modules");
+            std::fs::create_dir_all(&staging_modules)?;
+            for module in &modules {
+                if let Some(file_name) = module.file_name() {
+                    std::fs::copy(module, staging_modules.join(file_name))?;
+                }
+            }
+        }
 
-impl Default for OpenSSLLibrary {
-    fn default() -> Self {
-        Self::new()
+        let engines = self.engine_artifacts(context);
+        let staging_engines = staging_lib.join(self.engines_directory_name());
+        if !engines.is_empty() {
+            std::fs::create_dir_all(&staging_engines)?;
+            for engine in &engines {
+                if let Some(file_name) = engine.file_name() {
+                    std::fs::copy(engine, staging_engines.join(file_name))?;
+                }
+            }
+        }
+
+        write_checksums(
+            &staging,
+            &binaries_in(&[
+                staging_lib.clone(),
+                staging_lib.join("ossl-modules"),
+                staging_engines.clone(),
+            ]),
+        )?;
+        copy_directory(&prefix.join("include"), &staging.join("include"))?;
+        self.copy_licenses(context, &staging)?;
+        for file in &[SBOM_FILE, BINDINGS_FILE] {
+            let file = prefix.join(file);
+            if file.exists() {
+                std::fs::copy(&file, staging.join(file.file_name().unwrap_or_default()))?;
+            }
+        }
+        if let Some(pkg_config) = self.pkg_config_directory(context) {
+            copy_directory(&pkg_config, &staging_lib.join("pkgconfig"))?;
+        }
+
+        PackageManifest {
+            name: OPENSSL_DIRECTORY_NAME.to_string(),
+            version: self.version.name(),
+            target: context.target().to_string(),
+            profile: context.profile().to_string(),
+            source_revision: self.source_revision(context),
+            configure_arguments: self
+                .configure_command(context)?
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+            libraries: libraries
+                .iter()
+                .filter_map(|library| library.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .collect(),
+            modules_directory: if modules.is_empty() {
+                None
+            } else {
+                Some("lib/ossl-modules".to_string())
+            },
+            engines_directory: if engines.is_empty() {
+                None
+            } else {
+                Some(format!("lib/{}", self.engines_directory_name()))
+            },
+        }
+        .write(&staging.join("manifest.json"))?;
+
+        let archive = package_directory(&staging, context.is_windows())?;
+        write_published_checksum(&archive)?;
+        Ok(archive)
     }
-}
 
-impl OpenSSLLibrary {
-    pub fn new() -> Self {
-        Self {
-            source_location: LibraryLocation::Git(
-                GitLocation::github("syrel", "openssl")
-                    .branch("OpenSSL_1_1_1-stable-Windows-pkgconfig"),
-            ),
-            release_location: None,
-            options: Default::default(),
-            artefact: LibraryArtefact::Crypto,
+    /// Package the compiled library and return the files of a release: the archive with its
+    /// checksum, and the minisign signature if one was placed next to it
+    pub fn release_assets(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let archive = self.package(context)?;
+        let mut assets = vec![archive.clone()];
+        for extension in &[CHECKSUM_EXTENSION, SIGNATURE_EXTENSION] {
+            let file_name = format!(
+                "{}.{}",
+                archive
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                extension
+            );
+            let asset = archive.with_file_name(file_name);
+            if asset.exists() {
+                assets.push(asset);
+            }
         }
+        Ok(assets)
     }
 
-    pub fn be_ssl(mut self) -> Self {
-        self.artefact = LibraryArtefact::Ssl;
-        self
+    /// Upload the [`OpenSSLLibrary::release_assets`] to the GitHub release.
+    /// Returns the download urls
+    pub fn publish(
+        &self,
+        context: &LibraryCompilationContext,
+        release: &GitHubRelease,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        release
+            .clone()
+            .with_log_level(self.log_level())
+            .publish(&self.release_assets(context)?)
     }
 
-    pub fn be_crypto(mut self) -> Self {
-        self.artefact = LibraryArtefact::Crypto;
+    /// In offline mode nothing is fetched: the sources must already be checked out,
+    /// extracted or vendored, otherwise compilation fails immediately.
+    /// Also enabled by `CARGO_NET_OFFLINE=true`
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
         self
     }
 
-    pub fn with_release_location(mut self, release_location: Option<LibraryLocation>) -> Self {
-        self.release_location = release_location;
-        self
+    pub fn is_offline(&self) -> bool {
+        self.offline
+            || std::env::var("CARGO_NET_OFFLINE")
+                .map(|value| value == "true")
+                .unwrap_or(false)
+    }
+
+    fn apply_patches(&self, context: &LibraryCompilationContext) -> Result<(), Box<dyn Error>> {
+        if self.patches.is_empty() {
+            return Ok(());
+        }
+        if let Some(local_source) = &self.local_source {
+            return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                "Patches are not applied to the local checkout {}, apply them there instead",
+                local_source.display()
+            ))
+            .into());
+        }
+        let source_directory = self.source_directory(context);
+        let patches_directory = self.build_directory(context).join("patches");
+        for patch in &self.patches {
+            patch.apply(&source_directory, &patches_directory)?;
+        }
+        Ok(())
+    }
+
+    /// `perl Configure` with all options, prepared to run in the build directory
+    fn configure_command(
+        &self,
+        options: &LibraryCompilationContext,
+    ) -> Result<Command, OpenSSLBuildError> {
+        self.build_configure_command(options, true)
+    }
+
+    /// Without `locate_toolchains` the Android NDK, the Visual Studio environment and clang-cl
+    /// are neither looked up nor validated, so that [`OpenSSLLibrary::plan`] works
+    /// on machines that only describe a cross build
+    fn build_configure_command(
+        &self,
+        options: &LibraryCompilationContext,
+        locate_toolchains: bool,
+    ) -> Result<Command, OpenSSLBuildError> {
+        let mut command = self.command(self.perl());
+        command
+            .current_dir(self.build_directory(options))
+            .arg(self.source_directory(options).join("Configure"))
+            .arg(format!("--{}", options.profile()))
+            .arg(format!(
+                "--prefix={}",
+                self.native_library_prefix(options).display()
+            ))
+            .arg(format!(
+                "--openssldir={}",
+                self.directories
+                    .openssldir(&self.native_library_prefix(options))
+            ))
+            .arg(self.compiler(options)?)
+            .arg(format!(
+                "OPT_LEVEL={}",
+                self.optimization
+                    .map(|optimization| optimization.opt_level())
+                    .unwrap_or("3")
+            ));
+
+        if self.version.is_v3() {
+            // OpenSSL 3 installs into lib64 on some platforms
+            command.arg("--libdir=lib");
+        }
+
+        if self.is_static() {
+            command.arg("no-shared");
+        }
+        if self.fips {
+            if !self.version.is_v3() {
+                return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                    "The fips provider requires OpenSSL 3, but {} was selected",
+                    self.version.name()
+                )));
+            }
+            command.arg("enable-fips");
+        }
+        if self.legacy_provider {
+            if !self.version.is_v3() {
+                return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                    "The legacy provider requires OpenSSL 3, but {} was selected",
+                    self.version.name()
+                )));
+            }
+            command.arg("enable-legacy");
+        }
+        if self.quic {
+            if !self.version.supports_quic() {
+                return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                    "QUIC requires OpenSSL 3.2 or newer, but {} was selected",
+                    self.version.name()
+                )));
+            }
+            command.arg("enable-quic");
+        }
+        if self.ktls {
+            if !self.version.is_v3() {
+                return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                    "Kernel TLS requires OpenSSL 3, but {} was selected",
+                    self.version.name()
+                )));
+            }
+            // the other targets are reported once by `force_compile`
+            if supports_ktls(&options.target().to_string()) {
+                command.arg("enable-ktls");
+            }
+        }
+        if is_android(&options.target().to_string()) {
+            command.arg(format!("-D__ANDROID_API__={}", self.android_api(options)));
+            if locate_toolchains {
+                self.configure_android(options, &mut command)?;
+            }
+        }
+        self.configure_target_toolchain(options, &mut command, locate_toolchains)?;
+        let target = options.target().to_string();
+        if self.lto && is_msvc(&target) {
+            return Err(OpenSSLBuildError::UnsupportedConfiguration(
+                "Link-time optimization is only supported for GCC and Clang targets".to_string(),
+            ));
+        }
+        if self.exported_symbols.is_some() && is_msvc(&target) {
+            return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                "Restricting the exported symbols is not supported for {}",
+                target
+            )));
+        }
+        if !self.sanitizers.is_empty() && is_msvc(&target) {
+            return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                "Sanitizers are not supported for {}",
+                target
+            )));
+        }
+        command.args(
+            self.sanitizers
+                .iter()
+                .map(|sanitizer| sanitizer.enable_flag()),
+        );
+        for (tool, program) in self.tools().into_iter().chain(self.lto_tools(&target)) {
+            command.arg(format!("{}={}", tool.variable(), program));
+        }
+        // Configure adds CFLAGS and LDFLAGS of its environment to the flags of the target
+        let (cflags, ldflags) = self.compiler_flags(options);
+        let inherit = self.hermetic.is_none();
+        append_flags(&mut command, "CFLAGS", &cflags, inherit);
+        append_flags(&mut command, "LDFLAGS", &ldflags, inherit);
+        if self.reproducible {
+            command.envs(self.reproducible_environment(options));
+            if !is_msvc(&options.target().to_string()) {
+                // keep the source and build paths out of debug information and __FILE__
+                command
+                    .arg(format!(
+                        "-ffile-prefix-map={}=openssl",
+                        self.source_directory(options).display()
+                    ))
+                    .arg(format!(
+                        "-ffile-prefix-map={}=build",
+                        self.build_directory(options).display()
+                    ));
+            }
+        }
+
+        command.args(
+            self.disabled_features
+                .iter()
+                .map(|feature| feature.disable_flag()),
+        );
+        command.args(
+            self.disabled_protocols
+                .iter()
+                .map(|protocol| protocol.disable_flag()),
+        );
+        command.args(self.algorithms.configure_flags());
+        for engine in self.engines.enabled() {
+            if self.disabled_features.contains(&OpenSSLFeature::Engine) {
+                return Err(OpenSSLBuildError::UnsupportedConfiguration(format!(
+                    "The {} engine can not be built without engine support",
+                    engine.configure_name()
+                )));
+            }
+            engine
+                .check_target(&options.target().to_string())
+                .map_err(OpenSSLBuildError::UnsupportedConfiguration)?;
+        }
+        command.args(self.engines.configure_flags());
+        if let Some(api_level) = &self.api_level {
+            command.arg(format!("--api={}", api_level));
+        }
+        if let Some(zlib) = &self.zlib {
+            command.args(zlib.configure_args(options));
+        }
+        command.args(&self.configure_args);
+
+        Ok(command)
+    }
+
+    /// Identifies the sources being built, if they can not change without the revision changing
+    fn source_revision(&self, context: &LibraryCompilationContext) -> Option<String> {
+        if self.local_source.is_some() {
+            return None;
+        }
+        let revision = match &self.source_archive {
+            Some(source_archive) => source_archive
+                .sha256()
+                .unwrap_or_else(|| source_archive.url())
+                .to_string(),
+            None => git_revision(&self.source_directory(context))?,
+        };
+        // an unreadable patch fails later when it is applied
+        let patches = self
+            .patches
+            .iter()
+            .map(|patch| {
+                let digest = patch.digest().unwrap_or_default();
+                format!("{}:{}", patch.name(), digest)
+            })
+            .collect::<Vec<String>>();
+        Some(format!("{}{}", revision, patches.join(",")))
+    }
+
+    /// make (or nmake on Windows) prepared to run in the build directory
+    fn make_command(
+        &self,
+        options: &LibraryCompilationContext,
+    ) -> Result<Command, OpenSSLBuildError> {
+        self.build_make_command(options, true)
+    }
+
+    /// See [`OpenSSLLibrary::build_configure_command`] for `locate_toolchains`. Without it nmake
+    /// is not searched for and NASM is not downloaded
+    fn build_make_command(
+        &self,
+        options: &LibraryCompilationContext,
+        locate_toolchains: bool,
+    ) -> Result<Command, OpenSSLBuildError> {
+        let makefile_dir = self.build_directory(options);
+        let target = options.target().to_string();
+        if !locate_toolchains && is_msvc(&target) {
+            let mut command =
+                self.command(self.make_tool().unwrap_or_else(|| PathBuf::from("nmake")));
+            command.current_dir(&makefile_dir).args(
+                self.directories
+                    .make_variables(&self.native_library_prefix(options)),
+            );
+            command.envs(self.reproducible_environment(options));
+            return Ok(command);
+        }
+        let msvc_toolchain = self.msvc_toolchain.as_ref().filter(|_| is_msvc(&target));
+        if let Some(toolchain) = msvc_toolchain {
+            let environment = toolchain.environment(&target)?;
+            let path = environment
+                .iter()
+                .find(|(key, _)| key.to_string_lossy().eq_ignore_ascii_case("PATH"))
+                .map(|(_, value)| value.clone());
+            let make_tool = self.make_tool().unwrap_or_else(|| PathBuf::from("nmake"));
+            let nmake = which::which_in(&make_tool, path, &makefile_dir)
+                .map_err(|_| OpenSSLBuildError::ToolMissing(make_tool.display().to_string()))?;
+
+            let mut command = self.command(&nmake);
+            command.current_dir(&makefile_dir).envs(environment).args(
+                self.directories
+                    .make_variables(&self.native_library_prefix(options)),
+            );
+            command.envs(self.reproducible_environment(options));
+            self.provide_nasm(options, &mut command)?;
+            self.provide_clang_cl(options, &mut command)?;
+            Ok(command)
+        } else if is_msvc(&target) {
+            let compiler = cc::Build::new()
+                .opt_level(3)
+                .target(options.target().to_string().as_str())
+                .host(LibraryTarget::for_current_host().to_string().as_str())
+                .debug(options.is_debug())
+                .get_compiler();
+            let nmake = match self.make_tool() {
+                Some(make_tool) => make_tool,
+                None => compiler
+                    .path()
+                    .parent()
+                    .map(|build_tools_dir| build_tools_dir.join("nmake.exe"))
+                    .filter(|nmake| nmake.exists())
+                    .ok_or_else(|| {
+                        OpenSSLBuildError::ToolMissing(format!(
+                            "nmake.exe next to {}",
+                            compiler.path().display()
+                        ))
+                    })?,
+            };
+
+            let filtered_env: HashMap<OsString, OsString> = compiler
+                .env()
+                .iter()
+                .map(|(k, value)| (k.clone(), value.clone()))
+                .collect();
+
+            let mut command = self.command(&nmake);
+            command.current_dir(&makefile_dir).envs(filtered_env).args(
+                self.directories
+                    .make_variables(&self.native_library_prefix(options)),
+            );
+            command.envs(self.reproducible_environment(options));
+            self.provide_nasm(options, &mut command)?;
+            self.provide_clang_cl(options, &mut command)?;
+            Ok(command)
+        } else {
+            let mut command =
+                self.command(self.make_tool().unwrap_or_else(|| PathBuf::from("make")));
+            command
+                .current_dir(&makefile_dir)
+                .args(
+                    self.directories
+                        .make_variables(&self.native_library_prefix(options)),
+                )
+                .envs(self.reproducible_environment(options));
+
+            if locate_toolchains && is_android(&options.target().to_string()) {
+                self.configure_android(options, &mut command)?;
+            }
+
+            Ok(command)
+        }
+    }
+
+    /// Generates `fipsmodule.cnf` next to the installed fips provider. The freshly built `openssl`
+    /// can only run on the host, so cross-compiled providers are left for `openssl fipsinstall`
+    /// on the target
+    fn run_fipsinstall(&self, context: &LibraryCompilationContext) -> Result<(), Box<dyn Error>> {
+        if context.target().to_string() != LibraryTarget::for_current_host().to_string() {
+            self.warning(format!(
+                "Skipping fipsinstall of {}, because {} is cross-compiled; run `openssl fipsinstall` on the target",
+                self.name(),
+                context.target()
+            ));
+            return Ok(());
+        }
+
+        let prefix = self.native_library_prefix(context);
+        let modules = self.modules_directory(context);
+        let openssl = prefix.join("bin").join(if context.is_windows() {
+            "openssl.exe"
+        } else {
+            "openssl"
+        });
+
+        let status = Command::new(&openssl)
+            .arg("fipsinstall")
+            .arg("-out")
+            .arg(modules.join("fipsmodule.cnf"))
+            .arg("-module")
+            .arg(modules.join(self.provider_module_name("fips", context)))
+            .status()?;
+
+        if !status.success() {
+            return Err(format!("{} fipsinstall failed", openssl.display()).into());
+        }
+        Ok(())
+    }
+
+    /// Where the libraries are installed, always `lib` since the build passes `--libdir=lib`
+    pub fn library_directory(&self, context: &LibraryCompilationContext) -> PathBuf {
+        self.native_library_prefix(context).join("lib")
+    }
+
+    /// The directory where OpenSSL 3 installs its providers
+    pub fn modules_directory(&self, context: &LibraryCompilationContext) -> PathBuf {
+        let prefix = self.native_library_prefix(context);
+        match self.directories.modulesdir() {
+            Some(modulesdir) => InstallDirectories::resolve(&prefix, modulesdir),
+            None => self.library_directory(context).join("ossl-modules"),
+        }
+    }
+
+    /// The directory dynamic engines are installed into, `engines-3` or `engines-1.1`
+    /// of the library directory unless set with [`OpenSSLLibrary::with_enginesdir`]
+    pub fn engines_directory(&self, context: &LibraryCompilationContext) -> PathBuf {
+        let prefix = self.native_library_prefix(context);
+        match self.directories.enginesdir() {
+            Some(enginesdir) => InstallDirectories::resolve(&prefix, enginesdir),
+            None => self
+                .library_directory(context)
+                .join(self.engines_directory_name()),
+        }
+    }
+
+    /// The installed dynamic engines, to be shipped next to libcrypto
+    pub fn engine_artifacts(&self, context: &LibraryCompilationContext) -> Vec<PathBuf> {
+        let mut engines = std::fs::read_dir(self.engines_directory(context))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file())
+                    .collect::<Vec<PathBuf>>()
+            })
+            .unwrap_or_default();
+        engines.sort();
+        engines
+    }
+
+    /// The environment variables that point OpenSSL at the engines and providers shipped next to
+    /// libcrypto, `OPENSSL_ENGINES` and `OPENSSL_MODULES`, wherever the library was moved to.
+    /// The compiled in `ENGINESDIR` and `MODULESDIR` are paths of the build machine
+    pub fn runtime_vars(&self, lib_directory: &Path) -> Vec<(String, OsString)> {
+        let mut vars = vec![];
+        let engines = lib_directory.join(self.engines_directory_name());
+        if engines.is_dir() {
+            vars.push(("OPENSSL_ENGINES".to_string(), engines.into()));
+        }
+        let modules = lib_directory.join("ossl-modules");
+        if modules.is_dir() {
+            vars.push(("OPENSSL_MODULES".to_string(), modules.into()));
+        }
+        vars
+    }
+
+    /// Set [`OpenSSLLibrary::runtime_vars`] for a command, so that the libcrypto it loads finds
+    /// the engines and providers next to it. The environment of the current process is left alone
+    pub fn configure_runtime(&self, lib_directory: &Path, command: &mut Command) {
+        command.envs(self.runtime_vars(lib_directory));
+    }
+
+    /// The name of the engines directory next to libcrypto in a package
+    fn engines_directory_name(&self) -> &'static str {
+        if self.version.is_v3() {
+            "engines-3"
+        } else {
+            "engines-1.1"
+        }
+    }
+
+    fn provider_module_name(&self, provider: &str, context: &LibraryCompilationContext) -> String {
+        if context.is_windows() {
+            format!("{}.dll", provider)
+        } else if context.target().to_string().contains("apple") {
+            format!("{}.dylib", provider)
+        } else {
+            format!("{}.so", provider)
+        }
+    }
+
+    /// Whether providers are installed as loadable modules that have to be shipped with the library
+    fn ships_modules(&self) -> bool {
+        self.fips || self.legacy_provider
+    }
+
+    /// The legacy provider module, to be shipped next to libcrypto
+    pub fn legacy_artifacts(&self, context: &LibraryCompilationContext) -> Vec<PathBuf> {
+        if !self.legacy_provider {
+            return vec![];
+        }
+        vec![self
+            .modules_directory(context)
+            .join(self.provider_module_name("legacy", context))]
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
+    }
+
+    /// Every installed provider module, including the legacy and fips providers and the fips
+    /// configuration, to be shipped in `ossl-modules` next to libcrypto
+    pub fn provider_artifacts(&self, context: &LibraryCompilationContext) -> Vec<PathBuf> {
+        let mut modules = std::fs::read_dir(self.modules_directory(context))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file())
+                    .collect::<Vec<PathBuf>>()
+            })
+            .unwrap_or_default();
+        for artifact in self
+            .legacy_artifacts(context)
+            .into_iter()
+            .chain(self.fips_artifacts(context))
+        {
+            if !modules.contains(&artifact) {
+                modules.push(artifact);
+            }
+        }
+        modules.sort();
+        modules
+    }
+
+    /// The fips provider and its configuration, to be shipped next to libcrypto
+    pub fn fips_artifacts(&self, context: &LibraryCompilationContext) -> Vec<PathBuf> {
+        if !self.fips {
+            return vec![];
+        }
+        let modules = self.modules_directory(context);
+        let openssldir = InstallDirectories::resolve(
+            &self.native_library_prefix(context),
+            &self
+                .directories
+                .openssldir(&self.native_library_prefix(context)),
+        );
+        vec![
+            modules.join(self.provider_module_name("fips", context)),
+            if self.fips_install {
+                modules.join("fipsmodule.cnf")
+            } else {
+                openssldir.join("fipsmodule.cnf")
+            },
+        ]
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
+    }
+
+    /// Look for every tool the build needs and report all missing ones at once
+    pub fn check_requirements(
+        &self,
+        context: &LibraryCompilationContext,
+    ) -> Result<(), OpenSSLBuildError> {
+        let target = context.target().to_string();
+        let mut missing: Vec<MissingRequirement> = vec![];
+
+        let perl = self.perl();
+        require_perl(&perl, &self.required_perl_modules(&target), &mut missing);
+        if is_msvc(&target) {
+            require_native_windows_perl(&perl, &mut missing);
+        }
+        match self.make_tool() {
+            Some(make_tool) => require_tool(&make_tool.to_string_lossy(), &mut missing),
+            None if !is_msvc(&target) => require_tool("make", &mut missing),
+            None => {}
+        }
+        if is_msvc(&target)
+            && !self.disabled_features.contains(&OpenSSLFeature::Asm)
+            && self.nasm.is_none()
+        {
+            require_tool("nasm", &mut missing);
+        }
+        if is_android(&target) {
+            require_android_ndk(self.android_ndk(), &mut missing);
+        }
+        if let Some(pkcs11) = &self.pkcs11 {
+            for tool in pkcs11.module().required_tools() {
+                require_tool(tool, &mut missing);
+            }
+        }
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(OpenSSLBuildError::MissingRequirements(missing))
+        }
     }
 
-    pub fn compiler(&self, options: &LibraryCompilationContext) -> &str {
-        match options.target() {
-            LibraryTarget::X8664appleDarwin => "darwin64-x86_64-cc",
-            LibraryTarget::AArch64appleDarwin => "darwin64-arm64-cc",
-            LibraryTarget::X8664pcWindowsMsvc => "VC-WIN64A",
-            LibraryTarget::AArch64pcWindowsMsvc => "VC-WIN64-ARM",
-            LibraryTarget::X8664UnknownlinuxGNU => "linux-x86_64-clang",
-            LibraryTarget::AArch64UnknownlinuxGNU => "linux-aarch64",
-            LibraryTarget::AArch64LinuxAndroid => "android-arm64",
+    /// Perl modules used by Configure that some distributions package separately
+    fn required_perl_modules(&self, target: &str) -> Vec<&'static str> {
+        let mut modules = vec![];
+        if self.version.is_v3() {
+            modules.push("IPC::Cmd");
         }
+        if is_msvc(target) {
+            modules.push("Text::Template");
+        }
+        modules
+    }
+
+    pub fn compiler(
+        &self,
+        options: &LibraryCompilationContext,
+    ) -> Result<&'static str, OpenSSLBuildError> {
+        let target = options.target().to_string();
+        configure_target(&target).ok_or(OpenSSLBuildError::UnsupportedTarget(target))
     }
 }
 
@@ -78,6 +2827,15 @@ impl Library for OpenSSLLibrary {
     }
 
     fn release_location(&self) -> &LibraryLocation {
+        if self.is_offline() {
+            return &self.source_location;
+        }
+        if self.binary_version.as_deref() == Some(LATEST_RELEASE) {
+            return self
+                .latest_release()
+                .map(|(_, location)| location)
+                .unwrap_or(&self.source_location);
+        }
         self.release_location
             .as_ref()
             .unwrap_or_else(|| &self.source_location)
@@ -87,17 +2845,24 @@ impl Library for OpenSSLLibrary {
         match self.artefact {
             LibraryArtefact::Crypto => "crypto",
             LibraryArtefact::Ssl => "ssl",
+            LibraryArtefact::Both => "openssl",
         }
     }
 
     fn compiled_library_name(&self) -> CompiledLibraryName {
-        match self.artefact {
-            LibraryArtefact::Crypto => CompiledLibraryName::Matching("crypto".to_string()),
-            LibraryArtefact::Ssl => CompiledLibraryName::Matching("ssl".to_string()),
-        }
+        let name = match self.artefact {
+            LibraryArtefact::Crypto => "crypto",
+            LibraryArtefact::Ssl => "ssl",
+            // libssl is installed next to libcrypto
+            LibraryArtefact::Both => "crypto",
+        };
+        CompiledLibraryName::Matching(self.naming.base_name(name).to_string())
     }
 
     fn dependencies(&self) -> Option<&LibraryDependencies> {
+        if self.zlib.is_some() {
+            return Some(&self.dependencies);
+        }
         None
     }
 
@@ -110,117 +2875,284 @@ impl Library for OpenSSLLibrary {
     }
 
     fn force_compile(&self, options: &LibraryCompilationContext) -> Result<(), Box<dyn Error>> {
+        // the prebuilt archive was already resolved and installed by `ensure_sources`
+        if self.may_use_prebuilt() {
+            let asset_name = self.prebuilt_asset(options)?.name();
+            if self.is_prebuilt_installed(options, &asset_name) {
+                return Ok(());
+            }
+        }
+        self.check_requirements(options)?;
+
+        let target = options.target().to_string();
+        if self.ktls && !supports_ktls(&target) {
+            self.warning(format!(
+                "Not enabling kernel TLS, it is only supported for Linux targets, not {}",
+                target
+            ));
+        }
+
         let out_dir = self.native_library_prefix(options);
         if !out_dir.exists() {
-            std::fs::create_dir_all(&out_dir)
-                .unwrap_or_else(|_| panic!("Could not create {:?}", &out_dir));
+            std::fs::create_dir_all(&out_dir)?;
         }
 
-        let makefile_dir = options.build_root().join(self.name());
-        if !makefile_dir.join("makefile").exists() {
-            let mut command = Command::new("perl");
-            command
-                .current_dir(&makefile_dir)
-                .arg(self.source_directory(options).join("Configure"))
-                .arg(format!("--{}", options.profile()))
-                .arg(format!(
-                    "--prefix={}",
-                    self.native_library_prefix(options).display()
-                ))
-                .arg(format!(
-                    "--openssldir={}",
-                    self.native_library_prefix(options).display()
-                ))
-                .arg(self.compiler(options))
-                .arg("OPT_LEVEL=3");
+        let makefile_dir = self.build_directory(options);
+        let _lock = DirectoryLock::acquire(&makefile_dir, self.log_level())?;
+        let mut configure = self.configure_command(options)?;
+        let fingerprint = BuildFingerprint::new(
+            &configure,
+            self.source_revision(options),
+            &options.target().to_string(),
+            &options.profile().to_string(),
+            &self.post_processing_options(),
+        );
+        let fingerprint_file = makefile_dir.join(FINGERPRINT_FILE);
+        let fingerprint_matches = fingerprint.matches(&fingerprint_file);
 
-            if self.is_static() {
-                command.arg("no-shared");
+        if fingerprint_matches && fingerprint.is_reusable() {
+            // neither the sources nor the options changed since the last build
+            return Ok(());
+        }
+
+        if fingerprint_file.exists() {
+            std::fs::remove_file(&fingerprint_file)?;
+        }
+
+        let configure_stamp = makefile_dir.join(CONFIGURE_STAMP_FILE);
+        let makefile_exists = makefile_dir.join("makefile").exists();
+        let is_configured = makefile_exists
+            && fingerprint.configuration_matches(&configure_stamp)
+            && is_configured_for(&makefile_dir, self.compiler(options)?);
+
+        if !is_configured {
+            if makefile_exists {
+                // objects compiled with the previous options must not be reused
+                self.run_step(options, "clean", self.make_command(options)?.arg("clean"))?;
             }
-            if options.target().is_android() {
-                command.arg(format!(
-                    "-D__ANDROID_API__{}=",
-                    options.android_target_api()
-                ));
-                configure_android_path(&mut command);
+            if configure_stamp.exists() {
+                std::fs::remove_file(&configure_stamp)?;
             }
+            self.apply_patches(options)?;
 
-            let configure = command.status().unwrap();
+            let status = self.timer.time(BuildStage::Configure, || {
+                self.run_step(options, "configure", &mut configure)
+            })?;
 
-            if !configure.success() {
-                panic!("Could not configure {}", self.name());
+            if !status.success() {
+                return Err(OpenSSLBuildError::ConfigureFailed {
+                    library: self.name().to_string(),
+                    status,
+                }
+                .into());
             }
+            fingerprint.write_configuration(&configure_stamp)?;
         };
 
-        let make = if options.is_windows() {
-            let compiler = cc::Build::new()
-                .opt_level(3)
-                .target(options.target().to_string().as_str())
-                .host(LibraryTarget::for_current_host().to_string().as_str())
-                .debug(options.is_debug())
-                .get_compiler();
-            let build_tools_dir = compiler.path().parent().unwrap();
-            let nmake = build_tools_dir.join("nmake.exe");
-            if !nmake.exists() {
-                panic!("Could not find nmake.exe in {}", build_tools_dir.display());
-            };
+        self.write_exported_symbols(options)?;
+        let mut make = self.make_command(options)?;
+        let status = self.timer.time(BuildStage::Make, || {
+            self.run_step(options, "make", make.arg("build_sw"))
+        })?;
 
-            let filtered_env: HashMap<OsString, OsString> = compiler
-                .env()
-                .iter()
-                .map(|(k, value)| (k.clone(), value.clone()))
-                .collect();
+        if !status.success() {
+            return Err(OpenSSLBuildError::MakeFailed {
+                library: self.name().to_string(),
+                status,
+            }
+            .into());
+        }
 
-            Command::new(&nmake)
-                .current_dir(&makefile_dir)
-                .envs(filtered_env)
-                .arg("install_sw")
-                .status()
-                .unwrap()
-        } else {
-            let mut command = Command::new("make");
-            command.current_dir(&makefile_dir).arg("install_sw");
+        self.run_tests(options)?;
+
+        let mut install = self.make_command(options)?;
+        let status = self.timer.time(BuildStage::Install, || {
+            self.run_step(options, "install", install.arg("install_sw"))
+        })?;
+
+        if !status.success() {
+            return Err(OpenSSLBuildError::InstallFailed {
+                library: self.name().to_string(),
+                status,
+            }
+            .into());
+        }
 
-            if options.target().is_android() {
-                configure_android_path(&mut command);
+        if self.fips {
+            let mut install_fips = self.make_command(options)?;
+            let status = self.timer.time(BuildStage::Install, || {
+                self.run_step(options, "install_fips", install_fips.arg("install_fips"))
+            })?;
+
+            if !status.success() {
+                return Err(OpenSSLBuildError::InstallFailed {
+                    library: format!("the fips module of {}", self.name()),
+                    status,
+                }
+                .into());
             }
 
-            command.status().unwrap()
-        };
+            if self.fips_install {
+                self.run_fipsinstall(options)?;
+            }
+        }
 
-        if !make.success() {
-            panic!("Could not compile {}", self.name());
+        self.post_process(options)?;
+        // linked against the post-processed libcrypto, before the pkg-config files are relocated
+        self.build_pkcs11(options)?;
+        if let (Some(prefix), Some(pkg_config)) =
+            (&self.pkg_config_prefix, self.pkg_config_directory(options))
+        {
+            relocate_pkg_config(&pkg_config, &self.native_library_prefix(options), prefix)?;
         }
+        self.verify(options)?;
+        self.run_symbol_audit(options)?;
+        // the first directory holds libcrypto and libssl, bin for Windows DLLs
+        let license_directory = self
+            .compiled_library_directories(options)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| self.library_directory(options));
+        self.copy_licenses(options, &license_directory)?;
+        self.write_checksums(options)?;
+        self.write_cmake_package(options)?;
+        self.write_build_metadata(options)?;
+        if self.sbom {
+            self.write_sbom(options)?;
+        }
+        fingerprint.write(&fingerprint_file)?;
         Ok(())
     }
 
+    /// The options of everything that runs after Configure and is not part of its invocation,
+    /// so that changing one of them rebuilds instead of reusing the previous build
+    fn post_processing_options(&self) -> String {
+        serde_json::json!({
+            "directories": self.directories,
+            "exported_symbols": self.exported_symbols,
+            "verification": self.verification,
+            "symbol_audit": self.symbol_audit,
+            "required_symbols": self.required_symbols,
+            "rpath_install_names": self.rpath_install_names,
+            "sonames": self.sonames,
+            "install_names": self.install_names,
+            "flat_soname": self.flat_soname,
+            "pkg_config_prefix": self.pkg_config_prefix,
+            "strip": self.strip,
+            "signing": self.signing,
+            "sbom": self.sbom,
+            "naming": self.naming,
+            "fips_install": self.fips_install,
+            "pkcs11": self.pkcs11,
+        })
+        .to_string()
+    }
+
     fn compiled_library_directories(&self, context: &LibraryCompilationContext) -> Vec<PathBuf> {
+        let target = context.target().to_string();
         if context.is_unix() {
-            let lib = self.native_library_prefix(context).join("lib");
+            let lib = self.library_directory(context);
+            if self.ships_modules() {
+                return vec![lib, self.modules_directory(context)];
+            }
             return vec![lib];
         }
         if context.is_windows() {
             let lib = self
                 .native_library_prefix(context)
                 .join(if self.is_static() { "lib" } else { "bin" });
+            if self.ships_modules() {
+                return vec![lib, self.modules_directory(context)];
+            }
             return vec![lib];
         }
         vec![]
     }
 
-    fn ensure_requirements(&self, options: &LibraryCompilationContext) {
-        which::which("perl").expect("Could not find `perl`");
+    fn source_directory(&self, context: &LibraryCompilationContext) -> PathBuf {
+        match &self.local_source {
+            Some(local_source) => local_source.clone(),
+            None => context
+                .sources_root()
+                .join(OPENSSL_DIRECTORY_NAME)
+                .join(self.version.name()),
+        }
+    }
 
-        if options.is_unix() {
-            which::which("make").expect("Could not find `make`");
+    fn ensure_sources(&self, context: &LibraryCompilationContext) -> Result<(), Box<dyn Error>> {
+        // a prebuilt archive needs no sources
+        if self.use_prebuilt(context) {
+            return Ok(());
+        }
+        let source_directory = self.source_directory(context);
+        let _lock = source_directory
+            .parent()
+            .filter(|_| self.local_source.is_none())
+            .map(|directory| DirectoryLock::acquire(directory, self.log_level()))
+            .transpose()?;
+        if self.local_source.is_some() {
+            if !source_directory.join("Configure").exists() {
+                return Err(format!(
+                    "{} does not look like an OpenSSL source tree",
+                    source_directory.display()
+                )
+                .into());
+            }
+            return Ok(());
+        }
+        if self.is_offline() {
+            let is_vendored = self
+                .source_archive
+                .as_ref()
+                .map(|source_archive| source_archive.is_vendored())
+                .unwrap_or(false);
+            if !is_vendored {
+                if source_directory.join("Configure").exists() {
+                    return Ok(());
+                }
+                return Err(format!(
+                    "Sources of {} are not available in {} and fetching them is disabled in offline mode",
+                    self.name(),
+                    source_directory.display()
+                )
+                .into());
+            }
         }
-        if options.is_windows() {
-            which::which("nasm").expect("Could not find `nasm`");
+        if let Some(source_archive) = &self.source_archive {
+            return self.timer.time(BuildStage::FetchSources, || {
+                source_archive.ensure_sources(&source_directory, &self.retry_policy())
+            });
         }
+        self.timer.time(BuildStage::FetchSources, || {
+            self.retry_policy()
+                .retry("Fetching the OpenSSL sources", || {
+                    // the mirror, proxy and token are passed to our own clone, never to the environment
+                    let result = if self.git_fetch.is_configured() {
+                        self.git_checkout
+                            .clone_into(&source_directory, &self.git_fetch)
+                    } else {
+                        self.location().ensure_sources(&source_directory, context)
+                    };
+                    // a failed clone leaves a partial checkout behind that would fail the next attempt
+                    if result.is_err()
+                        && source_directory.exists()
+                        && !source_directory.join("Configure").exists()
+                    {
+                        std::fs::remove_dir_all(&source_directory)?;
+                    }
+                    result
+                })
+        })?;
+        Ok(())
     }
 
+    /// The tools are only needed when compiling from sources, so they are checked
+    /// by [`OpenSSLLibrary::check_requirements`] at the start of `force_compile`,
+    /// where a missing tool can be returned as an error
+    fn ensure_requirements(&self, _options: &LibraryCompilationContext) {}
+
     fn native_library_prefix(&self, options: &LibraryCompilationContext) -> PathBuf {
-        options.build_root().join(self.name()).join("build")
+        self.build_directory(options).join("build")
     }
 
     fn native_library_include_headers(&self, context: &LibraryCompilationContext) -> Vec<PathBuf> {
@@ -238,7 +3170,7 @@ impl Library for OpenSSLLibrary {
     fn native_library_linker_libraries(&self, context: &LibraryCompilationContext) -> Vec<PathBuf> {
         let mut dirs = vec![];
 
-        let directory = self.native_library_prefix(context).join("lib");
+        let directory = self.library_directory(context);
 
         if directory.exists() {
             dirs.push(directory);
@@ -251,13 +3183,7 @@ impl Library for OpenSSLLibrary {
         &self,
         context: &LibraryCompilationContext,
     ) -> Vec<(OsString, OsString)> {
-        let crypto = self.clone().be_crypto();
-        let ssl = self.clone().be_ssl();
-
-        let crypto_library =
-            self.compiled_library_named(crypto.name(), crypto.compiled_library_name(), context);
-        let ssl_library =
-            self.compiled_library_named(ssl.name(), ssl.compiled_library_name(), context);
+        let (crypto_library, ssl_library) = self.compiled_libraries(context);
 
         vec![
             (
@@ -274,10 +3200,7 @@ impl Library for OpenSSLLibrary {
     }
 
     fn pkg_config_directory(&self, context: &LibraryCompilationContext) -> Option<PathBuf> {
-        let directory = self
-            .native_library_prefix(context)
-            .join("lib")
-            .join("pkgconfig");
+        let directory = self.library_directory(context).join("pkgconfig");
 
         if directory.exists() {
             return Some(directory);
@@ -297,20 +3220,20 @@ impl From<OpenSSLLibrary> for Box<dyn Library> {
     }
 }
 
-fn configure_android_path(command: &mut Command) {
-    let ndk = ndk_build::ndk::Ndk::from_env().unwrap();
-
-    let new_path = format!(
-        "{}:{}",
-        ndk.toolchain_dir().unwrap().join("bin").display(),
-        std::env::var("PATH").expect("PATH must be set")
-    );
-
-    command.env("PATH", new_path);
-
-    let ndk_root = std::env::var("ANDROID_NDK")
-        .or_else(|_| std::env::var("NDK_HOME"))
-        .expect("ANDROID_NDK or NDK_HOME must be defined");
-
-    command.env("ANDROID_NDK_ROOT", ndk_root);
+/// Append to a flags variable set for the command, or else to the one it inherits
+fn append_flags(command: &mut Command, variable: &str, flags: &[String], inherit: bool) {
+    if flags.is_empty() {
+        return;
+    }
+    let value = command
+        .get_envs()
+        .find(|(key, _)| key.to_string_lossy() == variable)
+        .and_then(|(_, value)| value.map(|value| value.to_string_lossy().to_string()))
+        .or_else(|| std::env::var(variable).ok().filter(|_| inherit))
+        .filter(|value| !value.trim().is_empty())
+        .into_iter()
+        .chain(flags.iter().cloned())
+        .collect::<Vec<String>>()
+        .join(" ");
+    command.env(variable, value);
 }
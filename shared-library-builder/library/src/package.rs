@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Archive the `include` directory of the install prefix, which contains the headers
+/// generated for the target such as `opensslconf.h` and `configuration.h`
+pub fn package_headers(prefix: &Path, archive: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let include = prefix.join("include");
+    if !include.join("openssl").exists() {
+        return Err(format!("{} contains no OpenSSL headers", include.display()).into());
+    }
+    tar_gz(prefix, &[Path::new("include")], archive)
+}
+
+/// Describes a packaged build, stored as `manifest.json` in the archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageManifest {
+    pub name: String,
+    pub version: String,
+    pub target: String,
+    pub profile: String,
+    /// The commit of the sources, or the checksum of the source archive
+    pub source_revision: Option<String>,
+    pub configure_arguments: Vec<String>,
+    pub libraries: Vec<String>,
+    /// The directory of the OpenSSL 3 providers relative to the package, to point
+    /// `OPENSSL_MODULES` at wherever the package is unpacked
+    #[serde(default)]
+    pub modules_directory: Option<String>,
+    /// The directory of the dynamic engines relative to the package, to point
+    /// `OPENSSL_ENGINES` at wherever the package is unpacked
+    #[serde(default)]
+    pub engines_directory: Option<String>,
+}
+
+impl PackageManifest {
+    pub fn write(&self, file: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(file, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Archive the contents of the staging directory under a top-level directory of the same name.
+/// Windows packages are zipped, everything else is a gzipped tarball
+pub fn package_directory(staging: &Path, zip: bool) -> Result<PathBuf, Box<dyn Error>> {
+    let parent = staging
+        .parent()
+        .ok_or_else(|| format!("{} has no parent", staging.display()))?;
+    let name = staging
+        .file_name()
+        .ok_or_else(|| format!("{} has no file name", staging.display()))?;
+
+    if !zip {
+        return tar_gz(
+            parent,
+            &[Path::new(name)],
+            &parent.join(format!("{}.tar.gz", name.to_string_lossy())),
+        );
+    }
+
+    let archive = parent.join(format!("{}.zip", name.to_string_lossy()));
+    // bsdtar, shipped with Windows 10 and macOS, picks the format from the extension
+    let status = Command::new("tar")
+        .arg("-a")
+        .arg("-cf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(parent)
+        .arg(name)
+        .status()?;
+    if !status.success() {
+        return Err(format!("Could not create {}", archive.display()).into());
+    }
+    Ok(archive)
+}
+
+/// Copy a directory tree, following symlinks
+pub fn copy_directory(source: &Path, destination: &Path) -> Result<(), Box<dyn Error>> {
+    std::fs::create_dir_all(destination)?;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let target = destination.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_directory(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Create a gzipped tarball of the given paths, relative to the directory
+pub fn tar_gz(
+    directory: &Path,
+    paths: &[&Path],
+    archive: &Path,
+) -> Result<PathBuf, Box<dyn Error>> {
+    if let Some(parent) = archive.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let status = Command::new("tar")
+        .arg("-czf")
+        .arg(archive)
+        .arg("-C")
+        .arg(directory)
+        .args(paths)
+        .status()?;
+
+    if !status.success() {
+        return Err(format!("Could not create {}", archive.display()).into());
+    }
+    Ok(archive.to_path_buf())
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A unified diff that is applied to the OpenSSL sources before Configure runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SourcePatch {
+    File(PathBuf),
+    Inline { name: String, contents: Vec<u8> },
+}
+
+impl SourcePatch {
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self::File(path.into())
+    }
+
+    pub fn inline(name: impl Into<String>, contents: impl Into<Vec<u8>>) -> Self {
+        Self::Inline {
+            name: name.into(),
+            contents: contents.into(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::File(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+            Self::Inline { name, .. } => name.clone(),
+        }
+    }
+
+    /// The sha256 of the diff, so that editing a patch invalidates the build
+    /// The file of a patch that is read from disk
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::File(path) => Some(path),
+            Self::Inline { .. } => None,
+        }
+    }
+
+    pub fn digest(&self) -> Result<String, Box<dyn Error>> {
+        match self {
+            Self::File(path) => crate::source::sha256_file(path),
+            Self::Inline { contents, .. } => Ok(Sha256::digest(contents)
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect()),
+        }
+    }
+
+    /// Inline patches are written into the given directory so that git can read them
+    fn patch_file(&self, directory: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        match self {
+            Self::File(path) => to_absolute(path),
+            Self::Inline { name, contents } => {
+                if !directory.exists() {
+                    std::fs::create_dir_all(directory)?;
+                }
+                let path = directory.join(name);
+                std::fs::write(&path, contents)?;
+                to_absolute(&path)
+            }
+        }
+    }
+
+    /// Apply the patch to the source directory. Patches that are already applied are skipped,
+    /// so that the same source tree can be built again
+    pub fn apply(
+        &self,
+        source_directory: &Path,
+        patches_directory: &Path,
+    ) -> Result<(), Box<dyn Error>> {
+        let patch_file = self.patch_file(patches_directory)?;
+
+        let already_applied = Command::new("git")
+            .current_dir(source_directory)
+            .arg("apply")
+            .arg("--reverse")
+            .arg("--check")
+            .arg(&patch_file)
+            .output()?
+            .status
+            .success();
+
+        if already_applied {
+            return Ok(());
+        }
+
+        let status = Command::new("git")
+            .current_dir(source_directory)
+            .arg("apply")
+            .arg(&patch_file)
+            .status()?;
+
+        if !status.success() {
+            return Err(format!(
+                "Could not apply {} to {}",
+                self.name(),
+                source_directory.display()
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl From<PathBuf> for SourcePatch {
+    fn from(path: PathBuf) -> Self {
+        Self::File(path)
+    }
+}
+
+impl From<&Path> for SourcePatch {
+    fn from(path: &Path) -> Self {
+        Self::File(path.to_path_buf())
+    }
+}
+
+fn to_absolute(path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+    Ok(std::env::current_dir()?.join(path))
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::SourceArchive;
+
+/// A PKCS#11 module that lets OpenSSL use smartcards and HSMs
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Pkcs11Module {
+    /// The `pkcs11` engine of libp11, built with autotools
+    Libp11,
+    /// The `pkcs11` provider of latchset/pkcs11-provider for OpenSSL 3, built with meson
+    Pkcs11Provider,
+}
+
+impl Pkcs11Module {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Libp11 => "libp11",
+            Self::Pkcs11Provider => "pkcs11-provider",
+        }
+    }
+
+    /// The file that marks extracted sources
+    pub fn source_marker(&self) -> &'static str {
+        match self {
+            Self::Libp11 => "configure",
+            Self::Pkcs11Provider => "meson.build",
+        }
+    }
+
+    /// The tools the build of the module runs
+    pub fn required_tools(&self) -> &'static [&'static str] {
+        match self {
+            Self::Libp11 => &["sh", "make", "pkg-config"],
+            Self::Pkcs11Provider => &["meson", "ninja", "pkg-config"],
+        }
+    }
+}
+
+/// A PKCS#11 module built from its release archive against the installed OpenSSL, after
+/// OpenSSL itself, because it links against libcrypto. The engine is installed into the
+/// engines directory and the provider into the modules directory, so both are packaged
+/// together with the libraries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pkcs11Dependency {
+    module: Pkcs11Module,
+    source: SourceArchive,
+}
+
+impl Pkcs11Dependency {
+    /// libp11 from a release archive such as `libp11-0.4.12.tar.gz`, which ships `configure`
+    pub fn libp11(source: SourceArchive) -> Self {
+        Self {
+            module: Pkcs11Module::Libp11,
+            source,
+        }
+    }
+
+    /// pkcs11-provider from a release archive such as `pkcs11-provider-0.5.tar.xz`
+    pub fn pkcs11_provider(source: SourceArchive) -> Self {
+        Self {
+            module: Pkcs11Module::Pkcs11Provider,
+            source,
+        }
+    }
+
+    pub fn module(&self) -> Pkcs11Module {
+        self.module
+    }
+
+    pub fn source(&self) -> &SourceArchive {
+        &self.source
+    }
+
+    /// The commands that build and install the module against the OpenSSL install prefix.
+    /// `pkg_config_path` is the `lib/pkgconfig` directory of the prefix
+    pub fn build_commands(
+        &self,
+        source_directory: &Path,
+        build_directory: &Path,
+        prefix: &Path,
+        pkg_config_path: &Path,
+        engines_directory: &Path,
+    ) -> Vec<(&'static str, Command)> {
+        let mut commands = match self.module {
+            Pkcs11Module::Libp11 => {
+                let mut configure = Command::new("sh");
+                configure
+                    .current_dir(build_directory)
+                    .arg(source_directory.join("configure"))
+                    .arg(format!("--prefix={}", prefix.display()))
+                    .arg(format!("--with-enginesdir={}", engines_directory.display()));
+                let mut make = Command::new("make");
+                make.current_dir(build_directory);
+                let mut install = Command::new("make");
+                install.current_dir(build_directory).arg("install");
+                vec![
+                    ("configure", configure),
+                    ("make", make),
+                    ("install", install),
+                ]
+            }
+            Pkcs11Module::Pkcs11Provider => {
+                // the provider is installed into the modulesdir that libcrypto.pc declares
+                let mut setup = Command::new("meson");
+                setup
+                    .arg("setup")
+                    .arg(format!("--prefix={}", prefix.display()))
+                    .arg("--libdir=lib")
+                    .arg(build_directory)
+                    .arg(source_directory);
+                let mut compile = Command::new("meson");
+                compile.arg("compile").arg("-C").arg(build_directory);
+                let mut install = Command::new("meson");
+                install.arg("install").arg("-C").arg(build_directory);
+                vec![("setup", setup), ("compile", compile), ("install", install)]
+            }
+        };
+        for (_, command) in &mut commands {
+            command.env("PKG_CONFIG_PATH", pkg_config_path);
+        }
+        commands
+    }
+
+    /// Where the sources are extracted to, in the sources root of the build
+    pub fn source_directory(&self, sources_root: &Path) -> PathBuf {
+        sources_root.join("pkcs11").join(self.module.name())
+    }
+}
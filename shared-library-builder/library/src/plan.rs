@@ -0,0 +1,128 @@
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A command of the build as it would be run, with the environment variables
+/// that the builder sets on top of the inherited environment
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedCommand {
+    pub step: String,
+    pub program: String,
+    pub arguments: Vec<String>,
+    pub directory: Option<PathBuf>,
+    pub environment: Vec<(String, String)>,
+}
+
+impl PlannedCommand {
+    pub fn new(step: impl Into<String>, command: &Command) -> Self {
+        let mut environment = command
+            .get_envs()
+            .filter_map(|(key, value)| {
+                Some((
+                    key.to_string_lossy().to_string(),
+                    value?.to_string_lossy().to_string(),
+                ))
+            })
+            .collect::<Vec<(String, String)>>();
+        environment.sort();
+        Self {
+            step: step.into(),
+            program: command.get_program().to_string_lossy().to_string(),
+            arguments: command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+            directory: command
+                .get_current_dir()
+                .map(|directory| directory.to_path_buf()),
+            environment,
+        }
+    }
+}
+
+impl fmt::Display for PlannedCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# {}", self.step)?;
+        if let Some(directory) = &self.directory {
+            writeln!(f, "cd {}", directory.display())?;
+        }
+        for (key, value) in &self.environment {
+            writeln!(f, "{}={} \\", key, quote(value))?;
+        }
+        write!(f, "{}", quote(&self.program))?;
+        for argument in &self.arguments {
+            write!(f, " {}", quote(argument))?;
+        }
+        writeln!(f)
+    }
+}
+
+/// What a build would do: the resolved directories and the commands in the order they run
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildPlan {
+    pub library: String,
+    pub target: String,
+    pub source_directory: PathBuf,
+    pub build_directory: PathBuf,
+    pub install_prefix: PathBuf,
+    pub commands: Vec<PlannedCommand>,
+}
+
+impl fmt::Display for BuildPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# {} for {}", self.library, self.target)?;
+        writeln!(f, "# sources: {}", self.source_directory.display())?;
+        writeln!(f, "# build directory: {}", self.build_directory.display())?;
+        writeln!(f, "# install prefix: {}", self.install_prefix.display())?;
+        for command in &self.commands {
+            writeln!(f)?;
+            write!(f, "{}", command)?;
+        }
+        Ok(())
+    }
+}
+
+/// Quote for a POSIX shell when needed, so that the printed commands can be pasted
+fn quote(value: &str) -> String {
+    let is_plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if is_plain {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("--prefix=/opt/openssl"), "--prefix=/opt/openssl");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("-O2 -g"), "'-O2 -g'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote("$HOME"), "'$HOME'");
+    }
+
+    #[test]
+    fn planned_commands_can_be_pasted() {
+        let mut command = Command::new("perl");
+        command
+            .current_dir("/build")
+            .env("CFLAGS", "-O2 -g")
+            .arg("Configure")
+            .arg("linux-x86_64")
+            .arg("--prefix=/opt/my openssl");
+        assert_eq!(
+            PlannedCommand::new("configure", &command).to_string(),
+            "# configure\n\
+             cd /build\n\
+             CFLAGS='-O2 -g' \\\n\
+             perl Configure linux-x86_64 '--prefix=/opt/my openssl'\n"
+        );
+    }
+}
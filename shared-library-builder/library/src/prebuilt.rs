@@ -0,0 +1,571 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use crate::cache::DownloadCache;
+use crate::log::LogLevel;
+use crate::publish::{curl, retry_server_errors, run_curl, GITHUB_API};
+use crate::retry::RetryPolicy;
+use crate::source::sha256_file;
+
+/// Written into the build directory once a prebuilt archive was installed
+pub const PREBUILT_STAMP_FILE: &str = "openssl-prebuilt.stamp";
+
+/// Published next to each asset, in the format of `sha256sum`
+pub const CHECKSUM_EXTENSION: &str = "sha256";
+/// A detached minisign signature published next to each asset
+pub const SIGNATURE_EXTENSION: &str = "minisig";
+
+/// Servers answer with 404, S3 with 403, when there is no such file
+const MISSING_STATUS_CODES: &[&str] = &["403", "404", "410"];
+
+/// Prebuilt archives, as produced by [`crate::OpenSSLLibrary::package`], published as assets
+/// of a GitHub release or on any HTTP server. An asset is only used when its version, target
+/// and options hash match the requested build exactly.
+///
+/// The location of an asset is given by a url template with the placeholders
+/// `{version}` (the OpenSSL version), `{target}`, `{hash}` (the options hash) and
+/// `{asset}` (the file name, `openssl-{version}-{target}-{hash}.tar.gz` or `.zip`).
+///
+/// Each asset must be accompanied by `<asset>.sha256` and, when a public key is configured,
+/// by a minisign signature `<asset>.minisig`. Assets that can not be verified are refused
+/// unless [`PrebuiltBinaries::allow_unverified`] is set or `OPENSSL_ALLOW_UNVERIFIED_PREBUILT=1`.
+/// An asset that does not match its checksum or signature is always refused.
+///
+/// The checksum is downloaded from the same server as the asset, so it only protects against
+/// corrupted or truncated downloads. Only a signature checked against a public key configured
+/// with [`PrebuiltBinaries::with_public_key`] protects against a compromised server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrebuiltBinaries {
+    url_template: String,
+    public_key: Option<String>,
+    allow_unverified: bool,
+    /// Sent as a bearer token, never serialized
+    #[serde(skip)]
+    token: Option<String>,
+    /// The release the assets belong to, when they are published on GitHub
+    #[serde(default)]
+    github: Option<GitHubReleaseAssets>,
+}
+
+impl PrebuiltBinaries {
+    /// Assets are downloaded from `<base_url>/<asset name>`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::template(format!(
+            "{}/{{asset}}",
+            base_url.into().trim_end_matches('/')
+        ))
+    }
+
+    /// Assets are downloaded from the expanded template, for example
+    /// `https://my-bucket.s3.amazonaws.com/openssl/{version}/{target}/{asset}`
+    pub fn template(url_template: impl Into<String>) -> Self {
+        Self {
+            url_template: url_template.into(),
+            public_key: None,
+            allow_unverified: false,
+            token: None,
+            github: None,
+        }
+    }
+
+    /// The assets of the release `tag` of `https://github.com/<owner>/<repository>`.
+    /// With a token they are downloaded through the GitHub API, which also serves the assets
+    /// of private repositories
+    pub fn github(
+        owner: impl Into<String>,
+        repository: impl Into<String>,
+        tag: impl Into<String>,
+    ) -> Self {
+        let github = GitHubReleaseAssets {
+            owner: owner.into(),
+            repository: repository.into(),
+            tag: tag.into(),
+        };
+        let mut binaries = Self::new(format!(
+            "https://github.com/{}/{}/releases/download/{}",
+            github.owner, github.repository, github.tag
+        ));
+        binaries.github = Some(github);
+        binaries
+    }
+
+    /// Also require a minisign signature made with the key, given in the base64 form
+    /// that `minisign -P` accepts
+    pub fn with_public_key(mut self, public_key: impl Into<String>) -> Self {
+        self.public_key = Some(public_key.into());
+        self
+    }
+
+    /// Use assets without a published checksum or signature
+    pub fn allow_unverified(mut self, allow_unverified: bool) -> Self {
+        self.allow_unverified = allow_unverified;
+        self
+    }
+
+    /// Authenticate downloads from private GitHub repositories or servers,
+    /// falls back to `OPENSSL_RELEASE_TOKEN`
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    fn token(&self) -> Option<String> {
+        self.token
+            .clone()
+            .or_else(|| std::env::var("OPENSSL_RELEASE_TOKEN").ok())
+            .filter(|token| !token.is_empty())
+    }
+
+    fn is_unverified_allowed(&self) -> bool {
+        self.allow_unverified
+            || std::env::var("OPENSSL_ALLOW_UNVERIFIED_PREBUILT")
+                .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+                .unwrap_or(false)
+    }
+
+    pub fn url_template(&self) -> &str {
+        self.url_template.as_str()
+    }
+
+    pub fn url(&self, asset: &PrebuiltAsset) -> String {
+        self.url_template
+            .replace("{version}", &asset.version)
+            .replace("{target}", &asset.target)
+            .replace("{hash}", &asset.options_hash)
+            .replace("{asset}", &asset.name())
+    }
+
+    /// Download and verify the asset. Returns `None` when there is no such asset
+    pub fn download(
+        &self,
+        asset: &PrebuiltAsset,
+        directory: &Path,
+        retry: &RetryPolicy,
+        log_level: LogLevel,
+    ) -> Result<Option<PathBuf>, Box<dyn Error>> {
+        let url = self.url(asset);
+        let asset_name = asset.name();
+        let cache = DownloadCache::from_env();
+        let published = self.published_checksum(&url, &asset_name, directory, retry)?;
+        if let Some(sha256) = &published {
+            // an archive downloaded before the asset was republished
+            let archive = directory.join(&asset_name);
+            if archive.exists() && sha256_file(&archive)? != *sha256 {
+                std::fs::remove_file(&archive)?;
+            }
+        }
+        if let (Some(cache), Some(sha256)) = (&cache, &published) {
+            let archive = directory.join(&asset_name);
+            if !archive.exists() && cache.restore(sha256, &archive) {
+                log_level.progress(format!(
+                    "Using {} from {}",
+                    asset_name,
+                    cache.directory().display()
+                ));
+            }
+        }
+
+        let archive = match self.fetch(&url, &asset_name, directory, retry)? {
+            Some(archive) => archive,
+            None => return Ok(None),
+        };
+        if let Err(error) = self.verify(
+            &url,
+            &asset_name,
+            &archive,
+            published.as_deref(),
+            directory,
+            retry,
+            log_level,
+        ) {
+            // do not keep a rejected download around for the next build
+            std::fs::remove_file(&archive)?;
+            return Err(error);
+        }
+        if let (Some(cache), Some(sha256)) = (&cache, &published) {
+            cache.store(sha256, &archive);
+        }
+        Ok(Some(archive))
+    }
+
+    /// The SHA-256 from `<asset>.sha256`, if it is published. Always downloaded again,
+    /// because a republished asset comes with a new checksum
+    fn published_checksum(
+        &self,
+        url: &str,
+        asset_name: &str,
+        directory: &Path,
+        retry: &RetryPolicy,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let checksum_name = format!("{}.{}", asset_name, CHECKSUM_EXTENSION);
+        let checksum_url = format!("{}.{}", url, CHECKSUM_EXTENSION);
+        remove_if_exists(&directory.join(&checksum_name))?;
+        let checksum = match self.fetch(&checksum_url, &checksum_name, directory, retry)? {
+            Some(checksum) => checksum,
+            None => return Ok(None),
+        };
+        let published = std::fs::read_to_string(&checksum)?;
+        let sha256 = published
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| format!("{} is empty", checksum_name))?
+            .to_lowercase();
+        Ok(Some(sha256))
+    }
+
+    fn verify(
+        &self,
+        url: &str,
+        asset_name: &str,
+        archive: &Path,
+        published: Option<&str>,
+        directory: &Path,
+        retry: &RetryPolicy,
+        log_level: LogLevel,
+    ) -> Result<(), Box<dyn Error>> {
+        let checksum_name = format!("{}.{}", asset_name, CHECKSUM_EXTENSION);
+        match published {
+            Some(expected) => {
+                let actual = sha256_file(archive)?;
+                if actual != expected {
+                    remove_if_exists(&directory.join(&checksum_name))?;
+                    return Err(format!(
+                        "Checksum mismatch for {}: expected {}, got {}",
+                        asset_name, expected, actual
+                    )
+                    .into());
+                }
+            }
+            None => self.unverified(log_level, &format!("{} is not published", checksum_name))?,
+        }
+
+        let public_key = match &self.public_key {
+            Some(public_key) => public_key,
+            None => return Ok(()),
+        };
+        let signature_name = format!("{}.{}", asset_name, SIGNATURE_EXTENSION);
+        let signature_url = format!("{}.{}", url, SIGNATURE_EXTENSION);
+        remove_if_exists(&directory.join(&signature_name))?;
+        let signature = match self.fetch(&signature_url, &signature_name, directory, retry)? {
+            Some(signature) => signature,
+            None => {
+                return self.unverified(log_level, &format!("{} is not published", signature_name))
+            }
+        };
+        let status = match Command::new("minisign")
+            .arg("-V")
+            .arg("-q")
+            .arg("-P")
+            .arg(public_key)
+            .arg("-m")
+            .arg(archive)
+            .arg("-x")
+            .arg(&signature)
+            .status()
+        {
+            Ok(status) => status,
+            Err(_) => {
+                return self.unverified(
+                    log_level,
+                    &format!(
+                        "minisign is needed to check the signature of {}",
+                        asset_name
+                    ),
+                )
+            }
+        };
+        if !status.success() {
+            std::fs::remove_file(&signature)?;
+            return Err(format!("The signature of {} is not valid", asset_name).into());
+        }
+        Ok(())
+    }
+
+    fn unverified(&self, log_level: LogLevel, reason: &str) -> Result<(), Box<dyn Error>> {
+        if self.is_unverified_allowed() {
+            log_level.warning(format!("Using an unverified prebuilt archive: {}", reason));
+            return Ok(());
+        }
+        Err(format!(
+            "Refusing an unverified prebuilt archive, {}. Set OPENSSL_ALLOW_UNVERIFIED_PREBUILT=1 to use it anyway",
+            reason
+        )
+        .into())
+    }
+
+    /// Download the url into the directory as `file_name` unless it is already there.
+    /// Returns `None` when the server has no such file
+    fn fetch(
+        &self,
+        url: &str,
+        file_name: &str,
+        directory: &Path,
+        retry: &RetryPolicy,
+    ) -> Result<Option<PathBuf>, Box<dyn Error>> {
+        std::fs::create_dir_all(directory)?;
+        let archive = directory.join(file_name);
+        if archive.exists() {
+            return Ok(Some(archive));
+        }
+
+        let token = self.token();
+        let url = match (&self.github, &token) {
+            // the download urls of private repositories answer 404 even with a token
+            (Some(github), Some(token)) => match github.asset_url(file_name, token, retry)? {
+                Some(asset_url) => asset_url,
+                None => return Ok(None),
+            },
+            _ => url.to_string(),
+        };
+
+        let partial = directory.join(format!("{}.part", file_name));
+        let downloaded = retry.retry(&format!("Downloading {}", url), || {
+            let mut command = Command::new("curl");
+            command
+                .arg("--fail")
+                .arg("--location")
+                .arg("--silent")
+                .arg("--show-error")
+                .arg("--write-out")
+                .arg("%{http_code}")
+                .arg("--output")
+                .arg(&partial);
+            let output = match &token {
+                // curl does not forward the header when a redirect leads to another host
+                Some(token) => {
+                    command
+                        .arg("--header")
+                        .arg("@-")
+                        .arg("--header")
+                        .arg("Accept: application/octet-stream")
+                        .arg(&url);
+                    run_with_header(command, &format!("Authorization: Bearer {}", token))?
+                }
+                None => command.arg(&url).output()?,
+            };
+            if output.status.success() {
+                return Ok(true);
+            }
+            if partial.exists() {
+                std::fs::remove_file(&partial)?;
+            }
+            // a missing file is an answer, other errors may be transient
+            let status_code = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if MISSING_STATUS_CODES.contains(&status_code.as_str()) {
+                return Ok(false);
+            }
+            Err::<bool, Box<dyn Error>>(format!("Could not download {}", url).into())
+        })?;
+        if !downloaded {
+            return Ok(None);
+        }
+
+        std::fs::rename(&partial, &archive)?;
+        Ok(Some(archive))
+    }
+}
+
+/// A release of a GitHub repository
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct GitHubReleaseAssets {
+    owner: String,
+    repository: String,
+    tag: String,
+}
+
+impl GitHubReleaseAssets {
+    /// The API url of the asset named `file_name`, which is downloaded with
+    /// `Accept: application/octet-stream`. `None` when there is no such release or asset
+    fn asset_url(
+        &self,
+        file_name: &str,
+        token: &str,
+        retry: &RetryPolicy,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let repository_url = format!("{}/repos/{}/{}", GITHUB_API, self.owner, self.repository);
+        let release_url = format!("{}/releases/tags/{}", repository_url, self.tag);
+        let (status, release) = retry.retry(&format!("Looking up {}", release_url), || {
+            retry_server_errors(run_curl(curl("GET", &release_url), token)?)
+        })?;
+        if status == 404 {
+            return Ok(None);
+        }
+        if status != 200 {
+            return Err(format!(
+                "Could not look up the release {}: HTTP {} {}",
+                self.tag, status, release
+            )
+            .into());
+        }
+        Ok(release["assets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|asset| asset["name"] == file_name)
+            .and_then(|asset| asset["id"].as_u64())
+            .map(|id| format!("{}/releases/assets/{}", repository_url, id)))
+    }
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Pass a header on stdin, so that secrets do not show up in the process list
+fn run_with_header(mut command: Command, header: &str) -> std::io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(format!("{}\n", header).as_bytes())?;
+    }
+    child.wait_with_output()
+}
+
+/// The tag of the newest release of a GitHub repository, read from the redirect of
+/// `https://github.com/<owner>/<repository>/releases/latest`, which needs no API token
+pub fn latest_github_release(owner: &str, repository: &str) -> Result<String, Box<dyn Error>> {
+    let url = format!(
+        "https://github.com/{}/{}/releases/latest",
+        owner, repository
+    );
+    let output = Command::new("curl")
+        .arg("--fail")
+        .arg("--location")
+        .arg("--silent")
+        .arg("--show-error")
+        .arg("--output")
+        .arg(if cfg!(windows) { "NUL" } else { "/dev/null" })
+        .arg("--write-out")
+        .arg("%{url_effective}")
+        .arg(&url)
+        .output()?;
+    if !output.status.success() {
+        return Err(format!("Could not resolve {}", url).into());
+    }
+    let effective = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    effective
+        .split("/releases/tag/")
+        .nth(1)
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| tag.to_string())
+        .ok_or_else(|| format!("{} has no releases", url).into())
+}
+
+/// Write `<archive>.sha256` next to the archive, to be published with it
+pub fn write_published_checksum(archive: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let file_name = archive
+        .file_name()
+        .ok_or_else(|| format!("{} has no file name", archive.display()))?
+        .to_string_lossy()
+        .to_string();
+    let checksum = archive.with_file_name(format!("{}.{}", file_name, CHECKSUM_EXTENSION));
+    std::fs::write(
+        &checksum,
+        format!("{}  {}\n", sha256_file(archive)?, file_name),
+    )?;
+    Ok(checksum)
+}
+
+/// Identifies a prebuilt archive by the OpenSSL version, the target and the options hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrebuiltAsset {
+    pub version: String,
+    pub target: String,
+    pub options_hash: String,
+    /// Windows archives are zip files
+    pub zip: bool,
+}
+
+impl PrebuiltAsset {
+    /// `openssl-<version>-<target>-<options hash>`, the name of the packaged directory
+    pub fn stem(&self) -> String {
+        format!(
+            "openssl-{}-{}-{}",
+            self.version, self.target, self.options_hash
+        )
+    }
+
+    /// The file name of the archive, the stem with `.tar.gz` or `.zip`
+    pub fn name(&self) -> String {
+        format!(
+            "{}.{}",
+            self.stem(),
+            if self.zip { "zip" } else { "tar.gz" }
+        )
+    }
+}
+
+/// Extract a downloaded archive into the directory, `tar` also extracts zip files on Windows
+pub fn unpack(archive: &Path, directory: &Path) -> Result<(), Box<dyn Error>> {
+    if directory.exists() {
+        std::fs::remove_dir_all(directory)?;
+    }
+    std::fs::create_dir_all(directory)?;
+    let status = Command::new("tar")
+        .arg("-xf")
+        .arg(archive)
+        .arg("-C")
+        .arg(directory)
+        .status()?;
+    if !status.success() {
+        return Err(format!("Could not extract {}", archive.display()).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(zip: bool) -> PrebuiltAsset {
+        PrebuiltAsset {
+            version: "3.0".to_string(),
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            options_hash: "0123456789abcdef".to_string(),
+            zip,
+        }
+    }
+
+    #[test]
+    fn asset_names() {
+        assert_eq!(
+            asset(false).name(),
+            "openssl-3.0-x86_64-unknown-linux-gnu-0123456789abcdef.tar.gz"
+        );
+        assert_eq!(
+            asset(true).name(),
+            "openssl-3.0-x86_64-unknown-linux-gnu-0123456789abcdef.zip"
+        );
+    }
+
+    #[test]
+    fn url_template_placeholders() {
+        let prebuilt =
+            PrebuiltBinaries::template("https://example.com/{version}/{target}/{hash}/{asset}");
+        assert_eq!(
+            prebuilt.url(&asset(false)),
+            "https://example.com/3.0/x86_64-unknown-linux-gnu/0123456789abcdef/openssl-3.0-x86_64-unknown-linux-gnu-0123456789abcdef.tar.gz"
+        );
+    }
+
+    #[test]
+    fn base_url_and_github_release() {
+        assert_eq!(
+            PrebuiltBinaries::new("https://example.com/openssl/").url_template(),
+            "https://example.com/openssl/{asset}"
+        );
+        assert_eq!(
+            PrebuiltBinaries::github("feenkcom", "libopenssl", "v1.0.0").url(&asset(true)),
+            "https://github.com/feenkcom/libopenssl/releases/download/v1.0.0/openssl-3.0-x86_64-unknown-linux-gnu-0123456789abcdef.zip"
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::{LogLevel, RetryPolicy};
+
+pub(crate) const GITHUB_API: &str = "https://api.github.com";
+const GITHUB_UPLOADS: &str = "https://uploads.github.com";
+
+/// A GitHub release that packaged archives are uploaded to. The release is created for the tag
+/// if it does not exist yet, assets with the same name are replaced
+#[derive(Debug, Clone)]
+pub struct GitHubRelease {
+    owner: String,
+    repository: String,
+    tag: String,
+    token: Option<String>,
+    retry: RetryPolicy,
+    log_level: LogLevel,
+}
+
+impl GitHubRelease {
+    pub fn new(
+        owner: impl Into<String>,
+        repository: impl Into<String>,
+        tag: impl Into<String>,
+    ) -> Self {
+        Self {
+            owner: owner.into(),
+            repository: repository.into(),
+            tag: tag.into(),
+            token: None,
+            retry: RetryPolicy::from_env(),
+            log_level: LogLevel::from_env().unwrap_or_default(),
+        }
+    }
+
+    /// The token needs the `contents: write` permission, it falls back to `GITHUB_TOKEN`
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_log_level(mut self, log_level: LogLevel) -> Self {
+        self.log_level = log_level;
+        self.retry = self.retry.with_log_level(log_level);
+        self
+    }
+
+    pub fn tag(&self) -> &str {
+        self.tag.as_str()
+    }
+
+    fn token(&self) -> Result<String, Box<dyn Error>> {
+        self.token
+            .clone()
+            .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+            .ok_or_else(|| "A token is needed to publish, set GITHUB_TOKEN".into())
+    }
+
+    /// Upload the files as assets of the release. Returns their download urls
+    pub fn publish(&self, assets: &[PathBuf]) -> Result<Vec<String>, Box<dyn Error>> {
+        let token = self.token()?;
+        let release = self.find_or_create_release(&token)?;
+        let release_id = release["id"]
+            .as_u64()
+            .ok_or("The GitHub release has no id")?;
+        let existing = release["assets"].as_array().cloned().unwrap_or_default();
+
+        let mut urls = vec![];
+        for asset in assets {
+            let name = asset
+                .file_name()
+                .ok_or_else(|| format!("{} has no file name", asset.display()))?
+                .to_string_lossy()
+                .to_string();
+            for previous in existing.iter().filter(|previous| previous["name"] == name) {
+                if let Some(id) = previous["id"].as_u64() {
+                    self.request(
+                        &token,
+                        "DELETE",
+                        &format!("{}/releases/assets/{}", self.repository_url(), id),
+                        None,
+                    )?;
+                }
+            }
+            self.log_level
+                .progress(format!("Uploading {} to {}", name, self.tag));
+            let uploaded = self.upload(&token, release_id, &name, asset)?;
+            urls.push(
+                uploaded["browser_download_url"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            );
+        }
+        Ok(urls)
+    }
+
+    fn repository_url(&self) -> String {
+        format!("{}/repos/{}/{}", GITHUB_API, self.owner, self.repository)
+    }
+
+    fn find_or_create_release(&self, token: &str) -> Result<Value, Box<dyn Error>> {
+        let (status, release) = self.request_with_status(
+            token,
+            "GET",
+            &format!("{}/releases/tags/{}", self.repository_url(), self.tag),
+            None,
+        )?;
+        if status == 200 {
+            return Ok(release);
+        }
+        if status != 404 {
+            return Err(format!(
+                "Could not look up the release {}: HTTP {}",
+                self.tag, status
+            )
+            .into());
+        }
+        self.request(
+            token,
+            "POST",
+            &format!("{}/releases", self.repository_url()),
+            Some(json!({ "tag_name": self.tag, "name": self.tag })),
+        )
+    }
+
+    fn upload(
+        &self,
+        token: &str,
+        release_id: u64,
+        name: &str,
+        file: &Path,
+    ) -> Result<Value, Box<dyn Error>> {
+        let url = format!(
+            "{}/repos/{}/{}/releases/{}/assets?name={}",
+            GITHUB_UPLOADS, self.owner, self.repository, release_id, name
+        );
+        let (status, response) = self.retry.retry(&format!("Uploading {}", name), || {
+            let mut command = curl("POST", &url);
+            command
+                .arg("--header")
+                .arg("Content-Type: application/octet-stream")
+                .arg("--data-binary")
+                .arg(format!("@{}", file.display()));
+            retry_server_errors(run_curl(command, token)?)
+        })?;
+        if !(200..300).contains(&status) {
+            return Err(format!("Could not upload {}: HTTP {} {}", name, status, response).into());
+        }
+        Ok(response)
+    }
+
+    fn request(
+        &self,
+        token: &str,
+        method: &str,
+        url: &str,
+        body: Option<Value>,
+    ) -> Result<Value, Box<dyn Error>> {
+        let (status, response) = self.request_with_status(token, method, url, body)?;
+        if !(200..300).contains(&status) {
+            return Err(format!("{} {} failed: HTTP {} {}", method, url, status, response).into());
+        }
+        Ok(response)
+    }
+
+    fn request_with_status(
+        &self,
+        token: &str,
+        method: &str,
+        url: &str,
+        body: Option<Value>,
+    ) -> Result<(u32, Value), Box<dyn Error>> {
+        self.retry.retry(&format!("{} {}", method, url), || {
+            let mut command = curl(method, url);
+            if let Some(body) = &body {
+                command
+                    .arg("--header")
+                    .arg("Content-Type: application/json")
+                    .arg("--data")
+                    .arg(body.to_string());
+            }
+            retry_server_errors(run_curl(command, token)?)
+        })
+    }
+}
+
+pub(crate) fn curl(method: &str, url: &str) -> Command {
+    let mut command = Command::new("curl");
+    command
+        .arg("--silent")
+        .arg("--show-error")
+        .arg("--location")
+        .arg("--request")
+        .arg(method)
+        .arg("--header")
+        .arg("@-")
+        .arg("--header")
+        .arg("Accept: application/vnd.github+json")
+        .arg("--write-out")
+        .arg("\n%{http_code}")
+        .arg(url);
+    command
+}
+
+/// Server errors and rate limiting are worth another attempt
+pub(crate) fn retry_server_errors(response: (u32, Value)) -> Result<(u32, Value), Box<dyn Error>> {
+    let (status, body) = &response;
+    if *status >= 500 || *status == 429 {
+        return Err(format!("HTTP {} {}", status, body).into());
+    }
+    Ok(response)
+}
+
+/// The token is passed on stdin so that it does not show up in the process list
+pub(crate) fn run_curl(mut command: Command, token: &str) -> Result<(u32, Value), Box<dyn Error>> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or("Could not pass the token to curl")?
+        .write_all(format!("Authorization: Bearer {}\n", token).as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!("curl failed with {}", output.status).into());
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+    let (body, status) = output
+        .trim_end()
+        .rsplit_once('\n')
+        .unwrap_or(("", output.trim()));
+    let status = status.trim().parse::<u32>()?;
+    let body = if body.trim().is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(body).unwrap_or_else(|_| Value::String(body.to_string()))
+    };
+    Ok((status, body))
+}
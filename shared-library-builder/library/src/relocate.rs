@@ -0,0 +1,314 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::OpenSSLBuildError;
+
+const LIBRARY_NAMES: &[&str] = &["libcrypto", "libssl"];
+
+/// Rewrite the install names of the dylibs in the directory, which embed the absolute path
+/// of the build machine, to `@rpath/<file name>`, including the reference of libssl to libcrypto
+pub fn use_rpath_install_names(lib_directory: &Path) -> Result<(), OpenSSLBuildError> {
+    let libraries = installed_libraries(lib_directory, ".dylib")?;
+    let install_names = libraries
+        .iter()
+        .map(|library| Ok((otool_install_name(library)?, rpath_install_name(library))))
+        .collect::<Result<Vec<(String, String)>, OpenSSLBuildError>>()?;
+
+    for library in &libraries {
+        let mut command = Command::new("install_name_tool");
+        command.arg("-id").arg(rpath_install_name(library));
+        for (old, new) in &install_names {
+            command.arg("-change").arg(old).arg(new);
+        }
+        run_tool(command.arg(library))?;
+    }
+    Ok(())
+}
+
+/// Give the shared objects in the directory an unversioned soname (`libssl.so` instead of
+/// `libssl.so.3`) and make libssl depend on the unversioned libcrypto
+pub fn flatten_sonames(lib_directory: &Path) -> Result<(), OpenSSLBuildError> {
+    let libraries = installed_libraries(lib_directory, ".so")?;
+    let sonames = libraries
+        .iter()
+        .filter_map(|library| {
+            let file_name = file_name(library);
+            let flat = file_name.split(".so").next()?.to_string() + ".so";
+            Some((file_name, flat))
+        })
+        .collect::<Vec<(String, String)>>();
+
+    for library in &libraries {
+        let flat = sonames
+            .iter()
+            .find(|(versioned, _)| versioned == &file_name(library))
+            .map(|(_, flat)| flat.clone())
+            .unwrap_or_else(|| file_name(library));
+
+        let mut command = Command::new("patchelf");
+        command.arg("--set-soname").arg(&flat);
+        for (versioned, flat) in &sonames {
+            command.arg("--replace-needed").arg(versioned).arg(flat);
+        }
+        run_tool(command.arg(library))?;
+
+        // the unversioned name is usually a symlink to the versioned file
+        let flat_path = lib_directory.join(&flat);
+        if flat_path != *library {
+            if flat_path.exists() || flat_path.symlink_metadata().is_ok() {
+                std::fs::remove_file(&flat_path)?;
+            }
+            std::fs::copy(library, &flat_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Give libcrypto and libssl explicit sonames, for example `libgtcrypto.so.1`, so that several
+/// copies of OpenSSL can be loaded into one process. libssl is made to depend on the new soname
+/// of libcrypto and a copy of each library is placed under its soname. Requires `patchelf`
+pub fn set_sonames(lib_directory: &Path, crypto: &str, ssl: &str) -> Result<(), OpenSSLBuildError> {
+    let libraries = installed_libraries(lib_directory, ".so")?;
+    let crypto_library = versioned_library(&libraries, "libcrypto")?;
+    let ssl_library = versioned_library(&libraries, "libssl")?;
+
+    run_tool(
+        Command::new("patchelf")
+            .arg("--set-soname")
+            .arg(crypto)
+            .arg(&crypto_library),
+    )?;
+    let mut command = Command::new("patchelf");
+    command.arg("--set-soname").arg(ssl);
+    // libssl may need the versioned or, after flattening, the unversioned libcrypto
+    for library in &libraries {
+        if file_name(library).starts_with("libcrypto.") {
+            command
+                .arg("--replace-needed")
+                .arg(file_name(library))
+                .arg(crypto);
+        }
+    }
+    run_tool(command.arg(&ssl_library))?;
+
+    copy_as(&crypto_library, &lib_directory.join(crypto))?;
+    copy_as(&ssl_library, &lib_directory.join(ssl))?;
+    Ok(())
+}
+
+/// Give libcrypto and libssl explicit install names, for example
+/// `@rpath/libgtcrypto.1.dylib`, including the reference of libssl to libcrypto, and place
+/// a copy of each library under the file name of its install name
+pub fn set_install_names(
+    lib_directory: &Path,
+    crypto: &str,
+    ssl: &str,
+) -> Result<(), OpenSSLBuildError> {
+    let libraries = installed_libraries(lib_directory, ".dylib")?;
+    let crypto_library = versioned_library(&libraries, "libcrypto")?;
+    let ssl_library = versioned_library(&libraries, "libssl")?;
+    let crypto_install_name = otool_install_name(&crypto_library)?;
+
+    run_tool(
+        Command::new("install_name_tool")
+            .arg("-id")
+            .arg(crypto)
+            .arg(&crypto_library),
+    )?;
+    run_tool(
+        Command::new("install_name_tool")
+            .arg("-id")
+            .arg(ssl)
+            .arg("-change")
+            .arg(&crypto_install_name)
+            .arg(crypto)
+            .arg(&ssl_library),
+    )?;
+
+    for (library, install_name) in &[(&crypto_library, crypto), (&ssl_library, ssl)] {
+        let install_file_name = Path::new(install_name).file_name().ok_or_else(|| {
+            OpenSSLBuildError::UnsupportedConfiguration(format!(
+                "{} is not a valid install name",
+                install_name
+            ))
+        })?;
+        copy_as(library, &lib_directory.join(install_file_name))?;
+    }
+    Ok(())
+}
+
+/// The installed library with the longest, most versioned, name
+fn versioned_library(libraries: &[PathBuf], library: &str) -> Result<PathBuf, OpenSSLBuildError> {
+    libraries
+        .iter()
+        .filter(|path| file_name(path).starts_with(&format!("{}.", library)))
+        .max_by_key(|path| file_name(path).len())
+        .cloned()
+        .ok_or_else(|| {
+            OpenSSLBuildError::PostProcessingFailed(format!("{} was not installed", library))
+        })
+}
+
+/// Replace the copy, or symlink, with the library, unless it is the library itself
+fn copy_as(library: &Path, copy: &Path) -> Result<(), OpenSSLBuildError> {
+    if copy == library {
+        return Ok(());
+    }
+    if copy.symlink_metadata().is_ok() {
+        std::fs::remove_file(copy)?;
+    }
+    std::fs::copy(library, copy)?;
+    Ok(())
+}
+
+/// The libcrypto and libssl files with the extension in their name that are not symlinks
+fn installed_libraries(
+    lib_directory: &Path,
+    extension: &str,
+) -> Result<Vec<PathBuf>, OpenSSLBuildError> {
+    let mut libraries = std::fs::read_dir(lib_directory)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.symlink_metadata()
+                .map(|metadata| metadata.file_type().is_file())
+                .unwrap_or(false)
+        })
+        .filter(|path| {
+            let name = file_name(path);
+            name.contains(extension)
+                && LIBRARY_NAMES
+                    .iter()
+                    .any(|library| name.starts_with(&format!("{}.", library)))
+        })
+        .collect::<Vec<PathBuf>>();
+    libraries.sort();
+    Ok(libraries)
+}
+
+fn otool_install_name(library: &Path) -> Result<String, OpenSSLBuildError> {
+    let output = Command::new("otool").arg("-D").arg(library).output()?;
+    if !output.status.success() {
+        return Err(OpenSSLBuildError::PostProcessingFailed(format!(
+            "otool could not read the install name of {}",
+            library.display()
+        )));
+    }
+    // the first line repeats the path of the library
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .nth(1)
+        .map(|line| line.trim().to_string())
+        .ok_or_else(|| {
+            OpenSSLBuildError::PostProcessingFailed(format!(
+                "{} has no install name",
+                library.display()
+            ))
+        })
+}
+
+fn rpath_install_name(library: &Path) -> String {
+    format!("@rpath/{}", file_name(library))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Run a post-processing tool, which has to be on the `PATH`
+pub(crate) fn run_tool(command: &mut Command) -> Result<(), OpenSSLBuildError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    if which::which(&program).is_err() {
+        return Err(OpenSSLBuildError::ToolMissing(program));
+    }
+    let status = command.status()?;
+    if !status.success() {
+        return Err(OpenSSLBuildError::PostProcessingFailed(format!(
+            "{} failed ({})",
+            program, status
+        )));
+    }
+    Ok(())
+}
+
+/// The prefix of relocatable pkg-config files: the install prefix relative to `lib/pkgconfig`
+pub const RELOCATABLE_PKG_CONFIG_PREFIX: &str = "${pcfiledir}/../..";
+
+/// Replace the absolute install prefix in the `.pc` files of the directory with the new prefix,
+/// both in the `prefix=` variable and wherever else the build wrote it out
+pub fn relocate_pkg_config(
+    pkg_config_directory: &Path,
+    install_prefix: &Path,
+    new_prefix: &str,
+) -> Result<(), OpenSSLBuildError> {
+    let install_prefix = install_prefix.to_string_lossy().to_string();
+    for entry in std::fs::read_dir(pkg_config_directory)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .map(|extension| extension != "pc")
+            .unwrap_or(true)
+        {
+            continue;
+        }
+        let contents = std::fs::read_to_string(&path)?;
+        let relocated = contents
+            .lines()
+            .map(|line| {
+                if line.starts_with("prefix=") {
+                    format!("prefix={}", new_prefix)
+                } else {
+                    line.replace(&install_prefix, "${prefix}")
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        std::fs::write(&path, relocated + "\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkg_config_files_are_relocated() {
+        let directory =
+            std::env::temp_dir().join(format!("libopenssl-pkgconfig-{}", std::process::id()));
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+        std::fs::create_dir_all(&directory).unwrap();
+        let install_prefix = Path::new("/build/openssl/x86_64-unknown-linux-gnu/build");
+        let pc = directory.join("libcrypto.pc");
+        std::fs::write(
+            &pc,
+            "prefix=/build/openssl/x86_64-unknown-linux-gnu/build\n\
+             libdir=/build/openssl/x86_64-unknown-linux-gnu/build/lib\n\
+             includedir=${prefix}/include\n\
+             \n\
+             Name: OpenSSL-libcrypto\n",
+        )
+        .unwrap();
+        let other = directory.join("README");
+        std::fs::write(&other, "/build/openssl/x86_64-unknown-linux-gnu/build").unwrap();
+
+        relocate_pkg_config(&directory, install_prefix, RELOCATABLE_PKG_CONFIG_PREFIX).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&pc).unwrap(),
+            "prefix=${pcfiledir}/../..\n\
+             libdir=${prefix}/lib\n\
+             includedir=${prefix}/include\n\
+             \n\
+             Name: OpenSSL-libcrypto\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&other).unwrap(),
+            "/build/openssl/x86_64-unknown-linux-gnu/build"
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuildStage {
+    FetchSources,
+    Configure,
+    Make,
+    Test,
+    Install,
+}
+
+impl BuildStage {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::FetchSources => "fetch sources",
+            Self::Configure => "configure",
+            Self::Make => "make",
+            Self::Test => "test",
+            Self::Install => "install",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: BuildStage,
+    pub duration: Duration,
+}
+
+/// How long each stage of a compilation took. Stages that were skipped,
+/// for example because a prebuilt binary was downloaded, are absent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildReport {
+    pub stages: Vec<StageTiming>,
+    pub total: Duration,
+    /// Steps that were skipped or fell back, such as the test suite of a cross-compiled build
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl BuildReport {
+    pub fn duration_of(&self, stage: BuildStage) -> Duration {
+        self.stages
+            .iter()
+            .filter(|timing| timing.stage == stage)
+            .map(|timing| timing.duration)
+            .sum()
+    }
+
+    pub fn print_summary(&self) {
+        for timing in &self.stages {
+            println!(
+                "{:>15}: {:.1}s",
+                timing.stage.name(),
+                timing.duration.as_secs_f64()
+            );
+        }
+        println!("{:>15}: {:.1}s", "total", self.total.as_secs_f64());
+        for warning in &self.warnings {
+            println!("warning: {}", warning);
+        }
+    }
+}
+
+/// Collects stage timings while the library compiles. Shared between clones,
+/// because the compilation flow only has shared access to the library
+#[derive(Debug, Clone, Default)]
+pub struct StageTimer {
+    timings: Arc<Mutex<Vec<StageTiming>>>,
+    warnings: Arc<Mutex<Vec<String>>>,
+}
+
+impl StageTimer {
+    pub fn time<T>(&self, stage: BuildStage, block: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = block();
+        self.timings.lock().unwrap().push(StageTiming {
+            stage,
+            duration: start.elapsed(),
+        });
+        result
+    }
+
+    pub fn warn(&self, warning: impl Into<String>) {
+        self.warnings.lock().unwrap().push(warning.into());
+    }
+
+    pub fn reset(&self) {
+        self.timings.lock().unwrap().clear();
+        self.warnings.lock().unwrap().clear();
+    }
+
+    pub fn report(&self, total: Duration) -> BuildReport {
+        BuildReport {
+            stages: self.timings.lock().unwrap().clone(),
+            total,
+            warnings: self.warnings.lock().unwrap().clone(),
+        }
+    }
+}
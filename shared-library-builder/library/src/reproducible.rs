@@ -0,0 +1,105 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::relocate::run_tool;
+use crate::source::sha256_file;
+use crate::OpenSSLBuildError;
+
+/// `SOURCE_DATE_EPOCH` from the environment, or the commit time of the sources.
+/// Trees without git history get a fixed timestamp
+pub fn source_date_epoch(source_directory: &Path) -> String {
+    if let Ok(epoch) = std::env::var("SOURCE_DATE_EPOCH") {
+        return epoch;
+    }
+    Command::new("git")
+        .current_dir(source_directory)
+        .arg("log")
+        .arg("-1")
+        .arg("--format=%ct")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|epoch| !epoch.is_empty())
+        .unwrap_or_else(|| "1".to_string())
+}
+
+/// Compare the libraries of two builds pairwise by SHA-256
+pub fn compare_builds(first: &[PathBuf], second: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    if first.len() != second.len() {
+        return Err("The builds produced a different number of libraries".into());
+    }
+    let mut differences = vec![];
+    for (first, second) in first.iter().zip(second.iter()) {
+        let first_hash = sha256_file(first)?;
+        let second_hash = sha256_file(second)?;
+        if first_hash != second_hash {
+            differences.push(format!(
+                "{} ({}) differs from {} ({})",
+                first.display(),
+                first_hash,
+                second.display(),
+                second_hash
+            ));
+        }
+    }
+    if !differences.is_empty() {
+        return Err(format!("The build is not reproducible:\n{}", differences.join("\n")).into());
+    }
+    Ok(())
+}
+
+/// Rewrite a static archive with its members sorted by name, so that it does not depend on
+/// the order in which the objects were listed when it was created
+pub fn sort_archive_members(archive: &Path, target: &str) -> Result<(), OpenSSLBuildError> {
+    let archive = archive.canonicalize()?;
+    let output = Command::new("ar").arg("t").arg(&archive).output()?;
+    if !output.status.success() {
+        return Err(OpenSSLBuildError::PostProcessingFailed(format!(
+            "ar could not list the members of {}",
+            archive.display()
+        )));
+    }
+    // the symbol table of Apple's ar is recreated by ranlib
+    let members = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|member| !member.starts_with("__.SYMDEF"))
+        .map(|member| member.to_string())
+        .collect::<Vec<String>>();
+    let mut sorted = members.clone();
+    sorted.sort();
+    if sorted == members {
+        return Ok(());
+    }
+    if sorted.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(OpenSSLBuildError::PostProcessingFailed(format!(
+            "{} has several members with the same name, they can not be sorted",
+            archive.display()
+        )));
+    }
+
+    let members_directory = archive.with_extension("members");
+    if members_directory.exists() {
+        std::fs::remove_dir_all(&members_directory)?;
+    }
+    std::fs::create_dir_all(&members_directory)?;
+    run_tool(
+        Command::new("ar")
+            .current_dir(&members_directory)
+            .arg("x")
+            .arg(&archive),
+    )?;
+    std::fs::remove_file(&archive)?;
+
+    let mut ar = Command::new("ar");
+    ar.current_dir(&members_directory);
+    if target.contains("-apple-") {
+        ar.env("ZERO_AR_DATE", "1").arg("rcs");
+    } else {
+        ar.arg("rcsD");
+    }
+    run_tool(ar.arg(&archive).args(&sorted))?;
+    std::fs::remove_dir_all(&members_directory)?;
+    Ok(())
+}
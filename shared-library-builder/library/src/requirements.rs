@@ -0,0 +1,189 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::process::Command;
+
+use crate::android::AndroidNdk;
+use crate::OpenSSLBuildError;
+
+/// Configure of both OpenSSL 1.1.1 and 3 requires perl 5.10
+const MINIMUM_PERL_VERSION: (u32, u32) = (5, 10);
+
+/// A tool or SDK that is needed to build OpenSSL but could not be found,
+/// with a hint how to install it on the current host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingRequirement {
+    pub name: String,
+    pub hint: String,
+}
+
+impl MissingRequirement {
+    pub fn new(name: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            hint: hint.into(),
+        }
+    }
+
+    /// A program that is not on the `PATH`
+    pub fn tool(name: &str) -> Self {
+        Self::new(name, install_hint(name))
+    }
+}
+
+impl Display for MissingRequirement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.hint)
+    }
+}
+
+/// Check that the program is on the `PATH`
+pub fn require_tool(name: &str, missing: &mut Vec<MissingRequirement>) {
+    if which::which(name).is_err() {
+        missing.push(MissingRequirement::tool(name));
+    }
+}
+
+/// Check that perl is installed, recent enough and provides the given modules.
+/// A perl that fails these checks makes Configure fail halfway with a cryptic message
+pub fn require_perl(perl: &Path, modules: &[&str], missing: &mut Vec<MissingRequirement>) {
+    if which::which(perl).is_err() {
+        missing.push(MissingRequirement::new(
+            perl.display().to_string(),
+            install_hint("perl"),
+        ));
+        return;
+    }
+
+    match perl_version(perl) {
+        Some(version) if version >= MINIMUM_PERL_VERSION => {}
+        Some((major, minor)) => missing.push(MissingRequirement::new(
+            format!("perl {}.{}", MINIMUM_PERL_VERSION.0, MINIMUM_PERL_VERSION.1),
+            format!("found perl {}.{}, {}", major, minor, install_hint("perl")),
+        )),
+        None => missing.push(MissingRequirement::new(
+            "perl",
+            format!(
+                "`perl -v` did not report a version, {}",
+                install_hint("perl")
+            ),
+        )),
+    }
+
+    for module in modules {
+        let is_available = Command::new(perl)
+            .arg(format!("-M{}", module))
+            .arg("-e1")
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false);
+        if !is_available {
+            missing.push(MissingRequirement::new(
+                format!("perl module {}", module),
+                module_hint(module),
+            ));
+        }
+    }
+}
+
+/// MSVC builds need a native Windows perl such as Strawberry or ActiveState Perl,
+/// the perl of MSYS2 or Cygwin produces unusable makefiles
+pub fn require_native_windows_perl(perl: &Path, missing: &mut Vec<MissingRequirement>) {
+    let os = match perl_osname(perl) {
+        Some(os) => os,
+        None => return,
+    };
+    if os == "MSWin32" {
+        return;
+    }
+    let flavor = match os.as_str() {
+        "msys" => "MSYS2",
+        "cygwin" => "Cygwin",
+        _ => os.as_str(),
+    };
+    missing.push(MissingRequirement::new(
+        "native Windows perl",
+        format!(
+            "{} is the {} perl, which breaks Configure for MSVC targets: {}, \
+             put it before {} on the PATH or select it with `with_perl` or OPENSSL_PERL",
+            perl.display(),
+            flavor,
+            install_hint("perl"),
+            flavor
+        ),
+    ));
+}
+
+/// The platform perl was built for, from `perl -V:osname` which prints `osname='MSWin32';`
+fn perl_osname(perl: &Path) -> Option<String> {
+    let output = Command::new(perl).arg("-V:osname").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let value = stdout.trim().strip_prefix("osname=")?;
+    Some(value.trim_end_matches(';').trim_matches('\'').to_string())
+}
+
+/// `major.minor` of the perl, parsed from `perl -v`
+fn perl_version(perl: &Path) -> Option<(u32, u32)> {
+    let output = Command::new(perl).arg("-v").output().ok()?;
+    let banner = String::from_utf8_lossy(&output.stdout);
+    // This is perl 5, version 30, subversion 0 (v5.30.0) built for ...
+    let version = banner.split("(v").nth(1)?.split(')').next()?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// The Android NDK is located through `ANDROID_NDK`, `NDK_HOME` or `ANDROID_NDK_HOME`
+pub fn require_android_ndk(
+    ndk: Result<AndroidNdk, OpenSSLBuildError>,
+    missing: &mut Vec<MissingRequirement>,
+) {
+    if let Err(error) = ndk {
+        missing.push(MissingRequirement::new(
+            "Android NDK",
+            format!(
+                "{}. Install it with `sdkmanager \"ndk;<version>\"` and point ANDROID_NDK to it, or use with_ndk",
+                error
+            ),
+        ));
+    }
+}
+
+fn module_hint(module: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("install it with `cpan {}`", module)
+    } else {
+        let package = format!("perl-{}", module.replace("::", "-"));
+        format!(
+            "install it with `cpan {}`, or the `{}` package on Fedora and RHEL",
+            module, package
+        )
+    }
+}
+
+fn install_hint(tool: &str) -> String {
+    let hint = if cfg!(target_os = "windows") {
+        match tool {
+            "perl" => "install Strawberry Perl (`choco install strawberryperl` or https://strawberryperl.com)",
+            "nasm" => "install NASM (`choco install nasm` or https://www.nasm.us), or build without assembly using `without_asm()`",
+            _ => "install it and make sure it is on the PATH",
+        }
+    } else if cfg!(target_os = "macos") {
+        match tool {
+            "perl" | "make" => "install the Xcode command line tools with `xcode-select --install`",
+            "nasm" => "install it with `brew install nasm`",
+            _ => "install it with Homebrew and make sure it is on the PATH",
+        }
+    } else {
+        match tool {
+            "perl" => "install it with `apt install perl` or `dnf install perl`",
+            "make" => "install it with `apt install make` or `dnf install make`",
+            "nasm" => "install it with `apt install nasm` or `dnf install nasm`",
+            _ => "install it with the package manager and make sure it is on the PATH",
+        }
+    };
+    hint.to_string()
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::log::LogLevel;
+
+/// How often network operations such as git clones and downloads are attempted before the
+/// build fails. The delay between attempts doubles up to a maximum, with a random jitter of
+/// up to a half of the delay so that parallel CI jobs do not retry in lockstep.
+///
+/// The number of attempts can be set with `OPENSSL_NETWORK_ATTEMPTS`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    /// Retries are reported unless the level is [`LogLevel::Silent`]
+    #[serde(default)]
+    log_level: LogLevel,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+            jitter: true,
+            log_level: LogLevel::from_env().unwrap_or_default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt
+    pub fn none() -> Self {
+        Self::default().with_attempts(1)
+    }
+
+    pub fn from_env() -> Self {
+        let policy = Self::default();
+        match std::env::var("OPENSSL_NETWORK_ATTEMPTS")
+            .ok()
+            .and_then(|attempts| attempts.trim().parse::<u32>().ok())
+        {
+            Some(attempts) => policy.with_attempts(attempts),
+            None => policy,
+        }
+    }
+
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial_delay: Duration, max_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self.max_delay = max_delay.max(initial_delay);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_log_level(mut self, log_level: LogLevel) -> Self {
+        self.log_level = log_level;
+        self
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// The delay before the given retry, starting with 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self
+            .initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        if !self.jitter {
+            return delay;
+        }
+        // the sub-second clock is random enough to spread out retries
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.subsec_nanos())
+            .unwrap_or_default();
+        delay + delay.mul_f64(f64::from(nanos % 1000) / 2000.0)
+    }
+
+    /// Run the operation until it succeeds or the attempts are used up, logging every retry
+    pub fn retry<T, E: Display>(
+        &self,
+        description: &str,
+        mut operation: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut attempt = 1;
+        loop {
+            match operation() {
+                Ok(value) => return Ok(value),
+                Err(error) if attempt < self.attempts => {
+                    let delay = self.delay(attempt);
+                    self.log_level.warning(format!(
+                        "{} failed (attempt {} of {}): {}. Retrying in {:.1}s",
+                        description,
+                        attempt,
+                        self.attempts,
+                        error,
+                        delay.as_secs_f64()
+                    ));
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_secs(2), Duration::from_secs(10))
+            .with_jitter(false);
+        assert_eq!(policy.delay(1), Duration::from_secs(2));
+        assert_eq!(policy.delay(2), Duration::from_secs(4));
+        assert_eq!(policy.delay(3), Duration::from_secs(8));
+        assert_eq!(policy.delay(4), Duration::from_secs(10));
+        assert_eq!(policy.delay(100), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn jitter_adds_at_most_a_half() {
+        let policy =
+            RetryPolicy::default().with_backoff(Duration::from_secs(2), Duration::from_secs(60));
+        let delay = policy.delay(1);
+        assert!(delay >= Duration::from_secs(2));
+        assert!(delay <= Duration::from_secs(3));
+    }
+
+    #[test]
+    fn retries_until_the_attempts_are_used_up() {
+        let policy = RetryPolicy::default()
+            .with_attempts(3)
+            .with_backoff(Duration::ZERO, Duration::ZERO)
+            .with_log_level(LogLevel::Silent);
+        let mut calls = 0;
+        let result: Result<(), String> = policy.retry("Failing", || {
+            calls += 1;
+            Err("failed".to_string())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+    }
+}
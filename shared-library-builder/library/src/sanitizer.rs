@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// A sanitizer to instrument the libraries with, to diagnose memory errors and undefined
+/// behaviour at the FFI boundary. Applications have to be built or preloaded with the same
+/// sanitizer runtime
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Sanitizer {
+    /// AddressSanitizer, `-fsanitize=address`
+    Address,
+    /// UndefinedBehaviorSanitizer, `-fsanitize=undefined`
+    Undefined,
+}
+
+impl Sanitizer {
+    /// The Configure option that enables the sanitizer, for example `enable-asan`
+    pub fn enable_flag(&self) -> &'static str {
+        match self {
+            Self::Address => "enable-asan",
+            Self::Undefined => "enable-ubsan",
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::Path;
+
+/// Written into the install prefix
+pub const SBOM_FILE: &str = "openssl.cdx.json";
+
+/// What went into a build, recorded as a CycloneDX software bill of materials
+#[derive(Debug, Clone)]
+pub struct SbomInputs {
+    pub version: String,
+    pub license: String,
+    pub target: String,
+    /// The git repository, source archive or local checkout the sources came from
+    pub source: String,
+    pub source_revision: Option<String>,
+    pub configure_arguments: Vec<String>,
+    pub patches: Vec<String>,
+    pub compiler: String,
+    /// Name and version of the libraries OpenSSL links against, such as zlib
+    pub dependencies: Vec<(String, Option<String>)>,
+}
+
+impl SbomInputs {
+    pub fn to_cyclonedx(&self) -> Value {
+        let mut properties = vec![
+            property("openssl:source", &self.source),
+            property("openssl:target", &self.target),
+            property("openssl:compiler", &self.compiler),
+        ];
+        properties.extend(
+            self.configure_arguments
+                .iter()
+                .map(|argument| property("openssl:configure-argument", argument)),
+        );
+        properties.extend(
+            self.patches
+                .iter()
+                .map(|patch| property("openssl:patch", patch)),
+        );
+
+        let mut openssl = json!({
+            "type": "library",
+            "bom-ref": "openssl",
+            "name": "openssl",
+            "version": self.version,
+            "licenses": [{ "license": { "id": self.license } }],
+            "properties": properties,
+        });
+        if let Some(revision) = &self.source_revision {
+            openssl["purl"] = json!(format!(
+                "pkg:generic/openssl@{}?vcs_revision={}",
+                self.version, revision
+            ));
+        }
+
+        let dependencies = self
+            .dependencies
+            .iter()
+            .map(|(name, version)| {
+                json!({
+                    "type": "library",
+                    "bom-ref": name,
+                    "name": name,
+                    "version": version.clone().unwrap_or_default(),
+                })
+            })
+            .collect::<Vec<Value>>();
+
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": {
+                "tools": [{
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+                "component": openssl,
+            },
+            "components": dependencies,
+            "dependencies": [{
+                "ref": "openssl",
+                "dependsOn": self.dependencies.iter().map(|(name, _)| name).collect::<Vec<&String>>(),
+            }],
+        })
+    }
+
+    pub fn write(&self, file: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(file, serde_json::to_string_pretty(&self.to_cyclonedx())?)?;
+        Ok(())
+    }
+}
+
+fn property(name: &str, value: &str) -> Value {
+    json!({ "name": name, "value": value })
+}
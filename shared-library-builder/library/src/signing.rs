@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::relocate::run_tool;
+use crate::OpenSSLBuildError;
+
+/// Identities used to sign the produced libraries: `codesign` for dylibs and
+/// `signtool` (Authenticode) for DLLs. Targets without an identity are left unsigned
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SigningConfig {
+    /// A codesign identity, for example `Developer ID Application: Company (TEAMID)`
+    codesign_identity: Option<String>,
+    /// Sign with the hardened runtime and a secure timestamp, as required for notarization
+    hardened_runtime: bool,
+    /// The SHA-1 thumbprint of a certificate in the Windows certificate store
+    signtool_certificate: Option<String>,
+    /// A `.pfx` certificate file, used instead of the certificate store
+    signtool_certificate_file: Option<PathBuf>,
+    #[serde(skip)]
+    signtool_password: Option<String>,
+    /// The cryptographic provider (CSP or KSP) and key container holding the private key of
+    /// `signtool_certificate_file`, so that no password is passed to signtool
+    signtool_key_container: Option<(String, String)>,
+    timestamp_url: Option<String>,
+}
+
+impl SigningConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_codesign_identity(mut self, identity: impl Into<String>) -> Self {
+        self.codesign_identity = Some(identity.into());
+        self
+    }
+
+    pub fn with_hardened_runtime(mut self, hardened_runtime: bool) -> Self {
+        self.hardened_runtime = hardened_runtime;
+        self
+    }
+
+    pub fn with_signtool_certificate(mut self, thumbprint: impl Into<String>) -> Self {
+        self.signtool_certificate = Some(thumbprint.into());
+        self
+    }
+
+    /// The password is never serialized, it falls back to `OPENSSL_SIGNTOOL_PASSWORD`.
+    /// signtool only takes it as the `/p` argument, so it is visible in the process list of the
+    /// machine while signing; prefer the certificate store or a key container on shared machines
+    pub fn with_signtool_certificate_file(
+        mut self,
+        file: impl Into<PathBuf>,
+        password: Option<String>,
+    ) -> Self {
+        self.signtool_certificate_file = Some(file.into());
+        self.signtool_password = password;
+        self
+    }
+
+    /// Sign with a certificate file (`.cer`) whose private key lives in a key container of a
+    /// cryptographic provider, for example a hardware token or
+    /// `Microsoft Software Key Storage Provider`, instead of a password protected `.pfx`
+    pub fn with_signtool_key_container(
+        mut self,
+        certificate: impl Into<PathBuf>,
+        provider: impl Into<String>,
+        key_container: impl Into<String>,
+    ) -> Self {
+        self.signtool_certificate_file = Some(certificate.into());
+        self.signtool_password = None;
+        self.signtool_key_container = Some((provider.into(), key_container.into()));
+        self
+    }
+
+    /// An RFC 3161 timestamp server for signtool, for example `http://timestamp.digicert.com`
+    pub fn with_timestamp_url(mut self, url: impl Into<String>) -> Self {
+        self.timestamp_url = Some(url.into());
+        self
+    }
+
+    /// Sign the library with the identity for its platform
+    pub fn sign(&self, library: &Path, target: &str) -> Result<(), OpenSSLBuildError> {
+        if target.contains("-apple-") {
+            if let Some(identity) = &self.codesign_identity {
+                return self.codesign(library, identity);
+            }
+        }
+        if target.contains("-windows-") {
+            return self.signtool(library);
+        }
+        Ok(())
+    }
+
+    fn codesign(&self, library: &Path, identity: &str) -> Result<(), OpenSSLBuildError> {
+        let mut command = Command::new("codesign");
+        command.arg("--force").arg("--sign").arg(identity);
+        if self.hardened_runtime {
+            command.arg("--options").arg("runtime").arg("--timestamp");
+        }
+        run_tool(command.arg(library))
+    }
+
+    fn signtool(&self, library: &Path) -> Result<(), OpenSSLBuildError> {
+        let mut command = Command::new("signtool");
+        command.arg("sign").arg("/fd").arg("SHA256");
+        if let Some(file) = &self.signtool_certificate_file {
+            command.arg("/f").arg(file);
+            if let Some((provider, key_container)) = &self.signtool_key_container {
+                command
+                    .arg("/csp")
+                    .arg(provider)
+                    .arg("/kc")
+                    .arg(key_container);
+            } else if let Some(password) = self
+                .signtool_password
+                .clone()
+                .or_else(|| std::env::var("OPENSSL_SIGNTOOL_PASSWORD").ok())
+            {
+                command.arg("/p").arg(password);
+            }
+        } else if let Some(thumbprint) = &self.signtool_certificate {
+            command.arg("/sha1").arg(thumbprint);
+        } else {
+            return Ok(());
+        }
+        if let Some(url) = &self.timestamp_url {
+            command.arg("/tr").arg(url).arg("/td").arg("SHA256");
+        }
+        run_tool(command.arg(library))
+    }
+}
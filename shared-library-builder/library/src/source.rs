@@ -0,0 +1,517 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::cache::DownloadCache;
+use crate::retry::RetryPolicy;
+use crate::version::OpenSSLVersion;
+
+/// A source tarball that is downloaded and verified against a known SHA-256
+/// before it is extracted, for example an official openssl.org release.
+/// Vendored archives are read from disk and never touch the network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceArchive {
+    url: String,
+    sha256: Option<String>,
+    vendored: bool,
+}
+
+impl SourceArchive {
+    pub fn new(url: impl Into<String>, sha256: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            sha256: Some(sha256.into().to_lowercase()),
+            vendored: false,
+        }
+    }
+
+    /// A source archive that is already present on disk, for example checked into a `vendor` directory
+    pub fn vendored(path: impl AsRef<Path>) -> Self {
+        Self {
+            url: path.as_ref().display().to_string(),
+            sha256: None,
+            vendored: true,
+        }
+    }
+
+    pub fn with_sha256(mut self, sha256: impl Into<String>) -> Self {
+        self.sha256 = Some(sha256.into().to_lowercase());
+        self
+    }
+
+    /// Look for an `openssl*.tar.gz` archive in the given directory
+    pub fn detect_vendored(directory: impl AsRef<Path>) -> Option<Self> {
+        let mut archives = std::fs::read_dir(directory.as_ref())
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| name.starts_with("openssl") && name.ends_with(".tar.gz"))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<PathBuf>>();
+        archives.sort();
+        archives.pop().map(Self::vendored)
+    }
+
+    /// The version of a release archive named like `openssl-3.0.13.tar.gz`
+    pub fn openssl_version(&self) -> Option<OpenSSLVersion> {
+        let name = self.file_name();
+        let stem = [".tar.gz", ".tgz", ".tar.xz", ".tar.bz2", ".zip"]
+            .iter()
+            .find_map(|extension| name.strip_suffix(extension))?;
+        if !stem.starts_with("openssl-") {
+            return None;
+        }
+        let version = OpenSSLVersion::Custom(stem.to_string());
+        version.major_minor().map(|_| version)
+    }
+
+    pub fn is_vendored(&self) -> bool {
+        self.vendored
+    }
+
+    /// An official release from https://www.openssl.org/source/, where `version` is for example `3.0.13`
+    pub fn openssl_release(version: impl AsRef<str>, sha256: impl Into<String>) -> Self {
+        Self::new(
+            format!(
+                "https://www.openssl.org/source/openssl-{}.tar.gz",
+                version.as_ref()
+            ),
+            sha256,
+        )
+    }
+
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+
+    pub fn file_name(&self) -> &str {
+        self.url
+            .rsplit(['/', '\\'])
+            .next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or("openssl.tar.gz")
+    }
+
+    /// Download the archive into the given directory unless it is already there
+    pub fn download(
+        &self,
+        directory: &Path,
+        retry: &RetryPolicy,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        if self.vendored {
+            let archive = PathBuf::from(&self.url);
+            if !archive.exists() {
+                return Err(
+                    format!("Vendored archive {} does not exist", archive.display()).into(),
+                );
+            }
+            return Ok(archive);
+        }
+
+        if !directory.exists() {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        let archive = directory.join(self.file_name());
+        if archive.exists() && self.verify(&archive).is_ok() {
+            return Ok(archive);
+        }
+
+        // archives with a known checksum are shared between workspaces
+        let cache = self
+            .sha256
+            .as_ref()
+            .and_then(|sha256| Some((DownloadCache::from_env()?, sha256)));
+        if let Some((cache, sha256)) = &cache {
+            if cache.restore(sha256, &archive) && self.verify(&archive).is_ok() {
+                return Ok(archive);
+            }
+        }
+
+        retry.retry(&format!("Downloading {}", &self.url), || {
+            let status = Command::new("curl")
+                .arg("--fail")
+                .arg("--location")
+                .arg("--silent")
+                .arg("--show-error")
+                .arg("--output")
+                .arg(&archive)
+                .arg(&self.url)
+                .status()?;
+            if !status.success() {
+                return Err(format!("Could not download {}", &self.url).into());
+            }
+            Ok::<(), Box<dyn Error>>(())
+        })?;
+
+        if let Some((cache, sha256)) = &cache {
+            if self.verify(&archive).is_ok() {
+                cache.store(sha256, &archive);
+            }
+        }
+        Ok(archive)
+    }
+
+    pub fn verify(&self, archive: &Path) -> Result<(), Box<dyn Error>> {
+        let expected = match &self.sha256 {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+        let actual = sha256_file(archive)?;
+        if &actual != expected {
+            return Err(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                archive.display(),
+                expected,
+                actual
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Extract the archive into the destination, dropping the top-level `openssl-X.Y.Z` directory
+    pub fn extract(&self, archive: &Path, destination: &Path) -> Result<(), Box<dyn Error>> {
+        if !destination.exists() {
+            std::fs::create_dir_all(destination)?;
+        }
+
+        let status = Command::new("tar")
+            .arg("-xzf")
+            .arg(archive)
+            .arg("--strip-components=1")
+            .arg("-C")
+            .arg(destination)
+            .status()?;
+
+        if !status.success() {
+            return Err(format!("Could not extract {}", archive.display()).into());
+        }
+        Ok(())
+    }
+
+    /// Download, verify and extract the archive into the source directory
+    pub fn ensure_sources(
+        &self,
+        source_directory: &Path,
+        retry: &RetryPolicy,
+    ) -> Result<(), Box<dyn Error>> {
+        if source_directory.join("Configure").exists() {
+            return Ok(());
+        }
+
+        let downloads = source_directory
+            .parent()
+            .map(|parent| parent.to_path_buf())
+            .unwrap_or_else(|| source_directory.to_path_buf());
+
+        let archive = self.download(&downloads, retry)?;
+        self.verify(&archive)?;
+        self.extract(&archive, source_directory)
+    }
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, Box<dyn Error>> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// A branch, tag or commit of a git repository
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GitReference {
+    Branch(String),
+    Tag(String),
+    Commit(String),
+}
+
+/// The repository and reference of the git source location, so that the clone can be run
+/// by the library itself with the [`GitFetchSettings`] applied to each git invocation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitCheckout {
+    repository: String,
+    reference: Option<GitReference>,
+}
+
+impl GitCheckout {
+    pub fn github(owner: impl AsRef<str>, repository: impl AsRef<str>) -> Self {
+        Self {
+            repository: format!(
+                "https://github.com/{}/{}.git",
+                owner.as_ref(),
+                repository.as_ref()
+            ),
+            reference: None,
+        }
+    }
+
+    pub fn with_reference(mut self, reference: GitReference) -> Self {
+        self.reference = Some(reference);
+        self
+    }
+
+    pub fn repository(&self) -> &str {
+        self.repository.as_str()
+    }
+
+    /// Shallow clone of the reference into the directory, unless it is already a checkout
+    pub fn clone_into(
+        &self,
+        directory: &Path,
+        settings: &GitFetchSettings,
+    ) -> Result<(), Box<dyn Error>> {
+        if directory.join(".git").exists() {
+            return Ok(());
+        }
+        let commands = match &self.reference {
+            Some(GitReference::Commit(commit)) => {
+                let mut init = Command::new("git");
+                init.arg("init").arg(directory);
+                let mut fetch = Command::new("git");
+                fetch
+                    .current_dir(directory)
+                    .arg("fetch")
+                    .arg("--depth")
+                    .arg("1")
+                    .arg(&self.repository)
+                    .arg(commit);
+                let mut checkout = Command::new("git");
+                checkout
+                    .current_dir(directory)
+                    .arg("checkout")
+                    .arg("--detach")
+                    .arg("FETCH_HEAD");
+                vec![init, fetch, checkout]
+            }
+            reference => {
+                let mut clone = Command::new("git");
+                clone.arg("clone").arg("--depth").arg("1");
+                if let Some(GitReference::Branch(name)) | Some(GitReference::Tag(name)) = reference
+                {
+                    clone.arg("--branch").arg(name);
+                }
+                clone.arg(&self.repository).arg(directory);
+                vec![clone]
+            }
+        };
+        for mut command in commands {
+            settings.configure(&mut command);
+            let status = command.status()?;
+            if !status.success() {
+                return Err(format!(
+                    "Could not clone {} into {}",
+                    self.repository,
+                    directory.display()
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Network settings for the git fetch of the sources. They are passed through `GIT_CONFIG_*`
+/// environment variables of each git invocation, so they never reach other processes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitFetchSettings {
+    /// Replaces `https://github.com/` in repository urls, for example `https://git.example.com/github/`
+    mirror: Option<String>,
+    /// An http(s) proxy such as `http://proxy.example.com:3128`
+    proxy: Option<String>,
+    /// A personal access token for private forks, never serialized
+    #[serde(skip)]
+    token: Option<String>,
+}
+
+impl GitFetchSettings {
+    pub fn with_mirror(mut self, mirror: impl Into<String>) -> Self {
+        self.mirror = Some(mirror.into());
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Authenticate the clone of private repositories on GitHub, or on the mirror if one is used
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Explicitly configured values take precedence over `OPENSSL_GIT_MIRROR`, `OPENSSL_GIT_PROXY`
+    /// and `OPENSSL_GIT_TOKEN`
+    pub fn mirror(&self) -> Option<String> {
+        self.mirror
+            .clone()
+            .or_else(|| std::env::var("OPENSSL_GIT_MIRROR").ok())
+    }
+
+    pub fn proxy(&self) -> Option<String> {
+        self.proxy
+            .clone()
+            .or_else(|| std::env::var("OPENSSL_GIT_PROXY").ok())
+    }
+
+    pub fn token(&self) -> Option<String> {
+        self.token
+            .clone()
+            .or_else(|| std::env::var("OPENSSL_GIT_TOKEN").ok())
+            .filter(|token| !token.is_empty())
+    }
+
+    pub fn git_config(&self) -> Vec<(String, String)> {
+        let mut config = vec![];
+        let mirror = self.mirror().map(|mirror| {
+            if mirror.ends_with('/') {
+                mirror
+            } else {
+                format!("{}/", mirror)
+            }
+        });
+        if let Some(mirror) = &mirror {
+            config.push((
+                format!("url.{}.insteadOf", mirror),
+                "https://github.com/".to_string(),
+            ));
+        }
+        if let Some(token) = self.token() {
+            // the header is scoped to the host, so the token is not sent anywhere else
+            let host = mirror.unwrap_or_else(|| "https://github.com/".to_string());
+            config.push((
+                format!("http.{}.extraheader", host),
+                format!(
+                    "AUTHORIZATION: basic {}",
+                    base64(format!("x-access-token:{}", token).as_bytes())
+                ),
+            ));
+        }
+        if let Some(proxy) = self.proxy() {
+            config.push(("http.proxy".to_string(), proxy));
+        }
+        config
+    }
+
+    /// True when git has to be run with any of the settings
+    pub fn is_configured(&self) -> bool {
+        !self.git_config().is_empty()
+    }
+
+    /// Pass the settings to a single git invocation, after the `GIT_CONFIG_*` entries
+    /// it inherits from the environment
+    pub fn configure(&self, command: &mut Command) {
+        let config = self.git_config();
+        if config.is_empty() {
+            return;
+        }
+
+        let existing = std::env::var("GIT_CONFIG_COUNT")
+            .ok()
+            .and_then(|count| count.parse::<usize>().ok())
+            .unwrap_or(0);
+
+        for (index, (key, value)) in config.iter().enumerate() {
+            command.env(format!("GIT_CONFIG_KEY_{}", existing + index), key);
+            command.env(format!("GIT_CONFIG_VALUE_{}", existing + index), value);
+        }
+        command.env("GIT_CONFIG_COUNT", (existing + config.len()).to_string());
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let triple = (u32::from(chunk[0]) << 16)
+            | (u32::from(*chunk.get(1).unwrap_or(&0)) << 8)
+            | u32::from(*chunk.get(2).unwrap_or(&0));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the test vectors of RFC 4648, section 10
+    #[test]
+    fn base64_rfc4648_vectors() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(b"fooba"), "Zm9vYmE=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn base64_uses_the_whole_alphabet() {
+        assert_eq!(base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+        assert_eq!(base64(&[0x00, 0x00, 0x00]), "AAAA");
+    }
+
+    #[test]
+    fn version_from_the_archive_name() {
+        assert_eq!(
+            SourceArchive::openssl_release("3.0.13", "").openssl_version(),
+            Some(OpenSSLVersion::Custom("openssl-3.0.13".to_string()))
+        );
+        assert_eq!(
+            SourceArchive::vendored("vendor/openssl-1.1.1w.tar.gz").openssl_version(),
+            Some(OpenSSLVersion::Custom("openssl-1.1.1w".to_string()))
+        );
+        assert_eq!(
+            SourceArchive::vendored("vendor/openssl.tar.gz").openssl_version(),
+            None
+        );
+    }
+
+    #[test]
+    fn token_header_is_scoped_to_the_mirror() {
+        let settings = GitFetchSettings::default()
+            .with_mirror("https://git.example.com/github")
+            .with_token("secret");
+        let config = settings.git_config();
+        assert!(config.contains(&(
+            "url.https://git.example.com/github/.insteadOf".to_string(),
+            "https://github.com/".to_string()
+        )));
+        assert!(config.contains(&(
+            "http.https://git.example.com/github/.extraheader".to_string(),
+            format!("AUTHORIZATION: basic {}", base64(b"x-access-token:secret"))
+        )));
+    }
+}
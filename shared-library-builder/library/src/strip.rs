@@ -0,0 +1,72 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::relocate::run_tool;
+use crate::targets::is_msvc;
+use crate::OpenSSLBuildError;
+
+/// Remove the debug information from the shared library and keep it in the debug directory:
+/// a `.dSYM` bundle on macOS, a `.debug` file linked with `--add-gnu-debuglink` for ELF targets.
+/// MSVC libraries carry no debug information, their `.pdb` files are copied instead.
+/// Returns the produced debug artifacts
+pub fn strip_library(
+    library: &Path,
+    target: &str,
+    debug_directory: &Path,
+) -> Result<Vec<PathBuf>, OpenSSLBuildError> {
+    std::fs::create_dir_all(debug_directory)?;
+    // the unversioned names are often symlinks
+    let library = library.canonicalize()?;
+    let file_name = library
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    if is_msvc(target) {
+        let pdb = library.with_extension("pdb");
+        if !pdb.exists() {
+            return Ok(vec![]);
+        }
+        let destination = debug_directory.join(pdb.file_name().unwrap_or_default());
+        std::fs::copy(&pdb, &destination)?;
+        return Ok(vec![destination]);
+    }
+
+    if target.contains("-apple-") {
+        let dsym = debug_directory.join(format!("{}.dSYM", file_name));
+        run_tool(Command::new("dsymutil").arg(&library).arg("-o").arg(&dsym))?;
+        run_tool(Command::new("strip").arg("-x").arg(&library))?;
+        return Ok(vec![dsym]);
+    }
+
+    let objcopy = objcopy();
+    let debug = debug_directory.join(format!("{}.debug", file_name));
+    run_tool(
+        Command::new(&objcopy)
+            .arg("--only-keep-debug")
+            .arg(&library)
+            .arg(&debug),
+    )?;
+    run_tool(
+        Command::new(&objcopy)
+            .arg("--strip-debug")
+            .arg("--strip-unneeded")
+            .arg(&library),
+    )?;
+    run_tool(
+        Command::new(&objcopy)
+            .arg(format!("--add-gnu-debuglink={}", debug.display()))
+            .arg(&library),
+    )?;
+    Ok(vec![debug])
+}
+
+/// The objcopy on the `PATH`, falling back to llvm-objcopy
+fn objcopy() -> &'static str {
+    for candidate in &["objcopy", "llvm-objcopy"] {
+        if which::which(candidate).is_ok() {
+            return candidate;
+        }
+    }
+    "objcopy"
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Command;
+
+use crate::targets::is_msvc;
+use crate::OpenSSLBuildError;
+
+/// Symbols every libcrypto build is expected to export
+pub const REQUIRED_CRYPTO_SYMBOLS: &[&str] = &[
+    "EVP_EncryptInit_ex",
+    "EVP_DigestInit_ex",
+    "EVP_PKEY_new",
+    "RAND_bytes",
+];
+
+/// Symbols every libssl build is expected to export
+pub const REQUIRED_SSL_SYMBOLS: &[&str] = &["SSL_CTX_new", "SSL_new", "SSL_connect", "SSL_accept"];
+
+/// The functions exported by a shared library or defined by a static one,
+/// listed with `nm` or `dumpbin /exports` for MSVC targets
+pub fn exported_symbols(
+    library: &Path,
+    target: &str,
+) -> Result<BTreeSet<String>, OpenSSLBuildError> {
+    if is_msvc(target) {
+        dumpbin_exports(library)
+    } else {
+        nm_symbols(library, target)
+    }
+}
+
+/// Check that every required symbol is exported by one of the libraries,
+/// reporting all missing ones at once
+pub fn audit_symbols(
+    libraries: &[&Path],
+    target: &str,
+    required: &[String],
+) -> Result<(), OpenSSLBuildError> {
+    if required.is_empty() {
+        return Ok(());
+    }
+    let mut exported = BTreeSet::new();
+    for library in libraries {
+        exported.extend(exported_symbols(library, target)?);
+    }
+    let missing = required
+        .iter()
+        .filter(|symbol| !exported.contains(symbol.as_str()))
+        .cloned()
+        .collect::<Vec<String>>();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(OpenSSLBuildError::VerificationFailed(format!(
+        "{} does not export {}",
+        libraries
+            .iter()
+            .map(|library| library.display().to_string())
+            .collect::<Vec<String>>()
+            .join(" or "),
+        missing.join(", ")
+    )))
+}
+
+fn nm_symbols(library: &Path, target: &str) -> Result<BTreeSet<String>, OpenSSLBuildError> {
+    let is_apple = target.contains("-apple-");
+    let is_shared = library
+        .extension()
+        .map(|extension| extension != "a")
+        .unwrap_or(false);
+
+    let nm = if which::which("nm").is_ok() {
+        "nm"
+    } else if which::which("llvm-nm").is_ok() {
+        "llvm-nm"
+    } else {
+        return Err(OpenSSLBuildError::ToolMissing("nm".to_string()));
+    };
+
+    let mut command = Command::new(nm);
+    if is_apple {
+        command.arg("-gU");
+    } else {
+        command.arg("--extern-only").arg("--defined-only");
+        if is_shared {
+            command.arg("--dynamic");
+        }
+    }
+    let output = command.arg(library).output()?;
+    if !output.status.success() {
+        return Err(OpenSSLBuildError::VerificationFailed(format!(
+            "{} could not list the symbols of {}",
+            nm,
+            library.display()
+        )));
+    }
+
+    Ok(parse_nm_symbols(
+        &String::from_utf8_lossy(&output.stdout),
+        is_apple,
+    ))
+}
+
+/// `<address> <type> <name>` lines, Mach-O symbols carry a leading underscore
+/// and ELF symbols may carry a version
+fn parse_nm_symbols(output: &str, is_apple: bool) -> BTreeSet<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(2))
+        .map(|name| {
+            if is_apple {
+                name.trim_start_matches('_')
+            } else {
+                name
+            }
+        })
+        .map(|name| name.split('@').next().unwrap_or(name).to_string())
+        .collect()
+}
+
+fn dumpbin_exports(library: &Path) -> Result<BTreeSet<String>, OpenSSLBuildError> {
+    which::which("dumpbin").map_err(|_| OpenSSLBuildError::ToolMissing("dumpbin".to_string()))?;
+
+    let output = Command::new("dumpbin")
+        .arg("/nologo")
+        .arg("/exports")
+        .arg(library)
+        .output()?;
+    if !output.status.success() {
+        return Err(OpenSSLBuildError::VerificationFailed(format!(
+            "dumpbin could not list the exports of {}",
+            library.display()
+        )));
+    }
+
+    Ok(parse_dumpbin_exports(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// The names of the `<ordinal> <hint> <rva> <name>` lines of `dumpbin /exports`,
+/// the hint and RVA are hexadecimal
+fn parse_dumpbin_exports(output: &str) -> BTreeSet<String> {
+    let is_hex = |column: &str| u64::from_str_radix(column, 16).is_ok();
+    output
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>())
+        .filter(|columns| {
+            columns.len() >= 4
+                && columns[0].parse::<u32>().is_ok()
+                && is_hex(columns[1])
+                && is_hex(columns[2])
+        })
+        .map(|columns| columns[3].to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nm_symbols_of_elf_libraries() {
+        let output = "\
+0000000000187a40 T EVP_sha256@@OPENSSL_3.0.0
+00000000001f2c10 T SSL_new@@OPENSSL_3.0.0
+                 w __gmon_start__
+00000000003a1000 B OPENSSL_ia32cap_P
+";
+        let symbols = parse_nm_symbols(output, false);
+        assert_eq!(
+            symbols.into_iter().collect::<Vec<String>>(),
+            vec!["EVP_sha256", "OPENSSL_ia32cap_P", "SSL_new"]
+        );
+    }
+
+    #[test]
+    fn nm_symbols_of_mach_o_libraries() {
+        let output = "\
+00000000000b1f40 T _EVP_sha256
+00000000000f2c10 T _SSL_new
+";
+        let symbols = parse_nm_symbols(output, true);
+        assert!(symbols.contains("EVP_sha256"));
+        assert!(symbols.contains("SSL_new"));
+        assert_eq!(symbols.len(), 2);
+    }
+
+    #[test]
+    fn dumpbin_exports() {
+        let output = "\
+Dump of file libcrypto-3-x64.dll
+
+File Type: DLL
+
+  Section contains the following exports for libcrypto-3-x64.dll
+
+    00000000 characteristics
+        3986 number of functions
+
+    ordinal hint RVA      name
+
+          1    0 000C1D20 ACCESS_DESCRIPTION_free
+          2    1 000C1D30 ACCESS_DESCRIPTION_it
+
+  Summary
+
+        1000 .data
+";
+        assert_eq!(
+            parse_dumpbin_exports(output)
+                .into_iter()
+                .collect::<Vec<String>>(),
+            vec!["ACCESS_DESCRIPTION_free", "ACCESS_DESCRIPTION_it"]
+        );
+    }
+}
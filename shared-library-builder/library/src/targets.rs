@@ -0,0 +1,72 @@
+/// Rust target triples the builder knows how to configure OpenSSL for: the ones the pinned
+/// shared-library-builder can express as a `LibraryTarget`. Targets are matched by triple, so
+/// that supporting another one only needs its Configure target here once `LibraryTarget` names it
+pub const SUPPORTED_TARGETS: &[&str] = &[
+    "x86_64-apple-darwin",
+    "aarch64-apple-darwin",
+    "x86_64-pc-windows-msvc",
+    "aarch64-pc-windows-msvc",
+    "x86_64-unknown-linux-gnu",
+    "aarch64-unknown-linux-gnu",
+    "aarch64-linux-android",
+];
+
+/// Every supported Rust target triple with the OpenSSL Configure target it maps to
+pub fn supported_targets() -> Vec<(&'static str, &'static str)> {
+    SUPPORTED_TARGETS
+        .iter()
+        .filter_map(|triple| Some((*triple, configure_target(triple)?)))
+        .collect()
+}
+
+/// The OpenSSL Configure target for a Rust target triple
+pub fn configure_target(triple: &str) -> Option<&'static str> {
+    match triple {
+        "x86_64-apple-darwin" => Some("darwin64-x86_64-cc"),
+        "aarch64-apple-darwin" => Some("darwin64-arm64-cc"),
+        "x86_64-pc-windows-msvc" => Some("VC-WIN64A"),
+        "aarch64-pc-windows-msvc" => Some("VC-WIN64-ARM"),
+        "x86_64-unknown-linux-gnu" => Some("linux-x86_64-clang"),
+        "aarch64-unknown-linux-gnu" => Some("linux-aarch64"),
+        "aarch64-linux-android" => Some("android-arm64"),
+        _ => None,
+    }
+}
+
+/// The architecture part of a target triple, for example `x86_64`
+pub fn architecture(triple: &str) -> &str {
+    triple.split('-').next().unwrap_or(triple)
+}
+
+pub fn is_android(triple: &str) -> bool {
+    triple.contains("-linux-android")
+}
+
+/// Kernel TLS offload is only implemented for Linux
+pub fn supports_ktls(triple: &str) -> bool {
+    triple.contains("-linux-") && !is_android(triple)
+}
+
+/// The Android ABI name of a target triple, as used by the NDK and `jniLibs`
+pub fn android_abi(triple: &str) -> Option<&'static str> {
+    match triple {
+        "aarch64-linux-android" => Some("arm64-v8a"),
+        _ => None,
+    }
+}
+
+/// MSVC targets are built with nmake, everything else with make
+pub fn is_msvc(triple: &str) -> bool {
+    triple.ends_with("-windows-msvc")
+}
+
+/// The system libraries a static libcrypto needs at link time
+pub fn static_system_libraries(triple: &str) -> Vec<&'static str> {
+    if is_msvc(triple) {
+        return vec!["ws2_32", "crypt32", "advapi32", "user32"];
+    }
+    if triple.contains("-linux-") && !is_android(triple) {
+        return vec!["dl", "pthread"];
+    }
+    vec![]
+}
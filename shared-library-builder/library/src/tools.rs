@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// A compiler or binutil that OpenSSL's Configure picks from the target by default
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum BuildTool {
+    Cc,
+    Cxx,
+    Ar,
+    Ranlib,
+    Ld,
+}
+
+impl BuildTool {
+    pub const ALL: [BuildTool; 5] = [Self::Cc, Self::Cxx, Self::Ar, Self::Ranlib, Self::Ld];
+
+    /// The Configure variable, for example `CC`
+    pub fn variable(&self) -> &'static str {
+        match self {
+            Self::Cc => "CC",
+            Self::Cxx => "CXX",
+            Self::Ar => "AR",
+            Self::Ranlib => "RANLIB",
+            Self::Ld => "LD",
+        }
+    }
+
+    /// The environment variable that overrides the tool, for example `OPENSSL_CC`
+    pub fn env_variable(&self) -> String {
+        format!("OPENSSL_{}", self.variable())
+    }
+}
@@ -0,0 +1,128 @@
+use shared_library_builder::{Library, LibraryCompilationContext, LibraryTarget};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::openssl_library::OPENSSL_DIRECTORY_NAME;
+use crate::package::copy_directory;
+use crate::OpenSSLLibrary;
+
+/// Headers that contain architecture specific configuration and have to be merged
+const ARCHITECTURE_HEADERS: &[&str] = &["opensslconf.h", "configuration.h"];
+
+/// Builds the library for x86_64 and arm64 macOS and merges the results into a universal binary
+pub fn compile_universal(
+    library: &OpenSSLLibrary,
+    context: &LibraryCompilationContext,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let x86_64 = LibraryCompilationContext::new(
+        context.sources_root(),
+        context.build_root(),
+        LibraryTarget::X8664appleDarwin,
+        context.is_debug(),
+    );
+    let arm64 = LibraryCompilationContext::new(
+        context.sources_root(),
+        context.build_root(),
+        LibraryTarget::AArch64appleDarwin,
+        context.is_debug(),
+    );
+
+    let universal_library = library.compile(&x86_64)?;
+    library.compile(&arm64)?;
+
+    let universal_prefix = universal_prefix(library, context);
+    let universal_lib = universal_prefix.join("lib");
+    std::fs::create_dir_all(&universal_lib)?;
+
+    let x86_64_libraries = library.artefact_libraries(&x86_64);
+    let arm64_libraries = library.artefact_libraries(&arm64);
+    let mut universal_libraries = vec![];
+    for (x86_64_library, arm64_library) in x86_64_libraries.iter().zip(arm64_libraries.iter()) {
+        universal_libraries.push(lipo(
+            &[x86_64_library.as_path(), arm64_library.as_path()],
+            &universal_lib,
+        )?);
+    }
+
+    merge_headers(
+        &library.native_library_prefix(&x86_64).join("include"),
+        &library.native_library_prefix(&arm64).join("include"),
+        &universal_prefix.join("include"),
+    )?;
+
+    // the universal counterpart of what compile returns
+    universal_libraries
+        .into_iter()
+        .find(|universal| universal.file_name() == universal_library.file_name())
+        .ok_or_else(|| format!("{} was not merged", universal_library.display()).into())
+}
+
+/// Merge the single architecture libraries into a fat library with the name of the first one
+pub(crate) fn lipo(libraries: &[&Path], destination: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let first = libraries.first().ok_or("lipo needs at least one library")?;
+    let file_name = first
+        .file_name()
+        .ok_or_else(|| format!("{} has no file name", first.display()))?;
+    std::fs::create_dir_all(destination)?;
+    let universal_library = destination.join(file_name);
+
+    let status = Command::new("lipo")
+        .arg("-create")
+        .args(libraries)
+        .arg("-output")
+        .arg(&universal_library)
+        .status()?;
+
+    if !status.success() {
+        return Err(format!("lipo could not create {}", universal_library.display()).into());
+    }
+    Ok(universal_library)
+}
+
+/// Where the universal library and its headers are assembled
+pub fn universal_prefix(library: &OpenSSLLibrary, context: &LibraryCompilationContext) -> PathBuf {
+    context
+        .build_root()
+        .join(OPENSSL_DIRECTORY_NAME)
+        .join(library.version().name())
+        .join("universal-apple-darwin")
+        .join("build")
+}
+
+/// Copy the x86_64 headers and replace the architecture specific ones with a
+/// wrapper that includes the right variant for the architecture being compiled
+pub(crate) fn merge_headers(
+    x86_64: &Path,
+    arm64: &Path,
+    destination: &Path,
+) -> Result<(), Box<dyn Error>> {
+    copy_directory(x86_64, destination)?;
+
+    for header in ARCHITECTURE_HEADERS {
+        let x86_64_header = x86_64.join("openssl").join(header);
+        let arm64_header = arm64.join("openssl").join(header);
+        if !x86_64_header.exists() || !arm64_header.exists() {
+            continue;
+        }
+
+        let stem = header.trim_end_matches(".h");
+        let openssl = destination.join("openssl");
+        std::fs::copy(&x86_64_header, openssl.join(format!("{}_x86_64.h", stem)))?;
+        std::fs::copy(&arm64_header, openssl.join(format!("{}_arm64.h", stem)))?;
+        std::fs::write(
+            openssl.join(header),
+            format!(
+                "#if defined(__x86_64__)\n\
+                 # include <openssl/{stem}_x86_64.h>\n\
+                 #elif defined(__arm64__) || defined(__aarch64__)\n\
+                 # include <openssl/{stem}_arm64.h>\n\
+                 #else\n\
+                 # error Unsupported architecture\n\
+                 #endif\n",
+                stem = stem
+            ),
+        )?;
+    }
+    Ok(())
+}
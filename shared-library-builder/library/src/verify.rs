@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use crate::linkage::check_dependencies;
+use crate::symbols::audit_symbols;
+use crate::{OpenSSLBuildError, REQUIRED_CRYPTO_SYMBOLS, REQUIRED_SSL_SYMBOLS};
+
+/// Load the built libcrypto and check the version it reports against the expected (major, minor),
+/// then load libssl while libcrypto stays loaded, so that libssl's reference to it resolves
+/// even though it is not on the search path of the loader. Returns the raw `OpenSSL_version_num`
+pub fn verify_libraries(
+    crypto_library: &Path,
+    ssl_library: &Path,
+    expected: Option<(u32, u32)>,
+) -> Result<u64, OpenSSLBuildError> {
+    let (_crypto, version_num) = verify_crypto(crypto_library, expected)?;
+    verify_ssl(ssl_library)?;
+    Ok(version_num)
+}
+
+/// Verify libraries that were built before, for example downloaded release assets:
+/// the required symbols are exported and, when the target is the host, the dependencies
+/// resolve and the libraries load and report the expected version.
+/// Returns the `OpenSSL_version_num` if the libraries were loaded
+pub fn verify_existing_libraries(
+    crypto_library: &Path,
+    ssl_library: &Path,
+    target: &str,
+    host: &str,
+    expected: Option<(u32, u32)>,
+) -> Result<Option<u64>, OpenSSLBuildError> {
+    for library in &[crypto_library, ssl_library] {
+        if !library.exists() {
+            return Err(OpenSSLBuildError::VerificationFailed(format!(
+                "{} does not exist",
+                library.display()
+            )));
+        }
+    }
+    let to_strings = |symbols: &[&str]| -> Vec<String> {
+        symbols.iter().map(|symbol| symbol.to_string()).collect()
+    };
+    audit_symbols(
+        &[crypto_library],
+        target,
+        &to_strings(REQUIRED_CRYPTO_SYMBOLS),
+    )?;
+    audit_symbols(&[ssl_library], target, &to_strings(REQUIRED_SSL_SYMBOLS))?;
+
+    if target != host {
+        return Ok(None);
+    }
+    check_dependencies(&[crypto_library, ssl_library], target)?;
+    verify_libraries(crypto_library, ssl_library, expected).map(Some)
+}
+
+fn verify_crypto(
+    library: &Path,
+    expected: Option<(u32, u32)>,
+) -> Result<(libloading::Library, u64), OpenSSLBuildError> {
+    let crypto = load(library)?;
+    let version_num = unsafe {
+        let function: libloading::Symbol<unsafe extern "C" fn() -> std::os::raw::c_ulong> = crypto
+            .get(b"OpenSSL_version_num\0")
+            .map_err(|error| failure(library, error))?;
+        function() as u64
+    };
+
+    // 0xMNN00PP0 for OpenSSL 3 and 0xMNNFFPPS for 1.1.1
+    let actual = (
+        (version_num >> 28) as u32,
+        ((version_num >> 20) & 0xff) as u32,
+    );
+    if let Some(expected) = expected {
+        if actual != expected {
+            return Err(OpenSSLBuildError::VerificationFailed(format!(
+                "{} reports version {}.{} ({:#x}), expected {}.{}",
+                library.display(),
+                actual.0,
+                actual.1,
+                version_num,
+                expected.0,
+                expected.1
+            )));
+        }
+    }
+    Ok((crypto, version_num))
+}
+
+fn verify_ssl(library: &Path) -> Result<(), OpenSSLBuildError> {
+    let ssl = load(library)?;
+    unsafe {
+        ssl.get::<unsafe extern "C" fn()>(b"OPENSSL_init_ssl\0")
+            .map_err(|error| failure(library, error))?;
+    }
+    Ok(())
+}
+
+fn load(library: &Path) -> Result<libloading::Library, OpenSSLBuildError> {
+    unsafe { libloading::Library::new(library) }.map_err(|error| failure(library, error))
+}
+
+fn failure(library: &Path, error: libloading::Error) -> OpenSSLBuildError {
+    OpenSSLBuildError::VerificationFailed(format!("{}: {}", library.display(), error))
+}
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use shared_library_builder::{GitLocation, LibraryLocation};
+use std::error::Error;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use crate::retry::RetryPolicy;
+use crate::source::{GitCheckout, GitFetchSettings, GitReference};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OpenSSLVersion {
+    V1_1_1,
+    V3_0,
+    V3_2,
+    /// A tag or branch of the upstream openssl/openssl repository,
+    /// for example `openssl-3.1.4` or `OpenSSL_1_1_1w`
+    Custom(String),
+}
+
+impl Default for OpenSSLVersion {
+    fn default() -> Self {
+        Self::V1_1_1
+    }
+}
+
+impl OpenSSLVersion {
+    /// The newest stable release of the upstream openssl/openssl repository, such as
+    /// `Custom("openssl-3.3.1")`. Pre-releases (`-alpha`, `-beta`) are skipped.
+    /// The tags are listed through the mirror, proxy and token of the fetch settings
+    pub fn latest(
+        settings: &GitFetchSettings,
+        retry: &RetryPolicy,
+    ) -> Result<Self, Box<dyn Error>> {
+        let repository = GitCheckout::github("openssl", "openssl");
+        let output = retry.retry(
+            "Listing the tags of openssl/openssl",
+            || -> Result<Output, Box<dyn Error>> {
+                let mut command = Command::new("git");
+                command
+                    .arg("ls-remote")
+                    .arg("--tags")
+                    .arg("--refs")
+                    .arg(repository.repository());
+                settings.configure(&mut command);
+                let output = command.output()?;
+                if !output.status.success() {
+                    return Err("Could not list the tags of openssl/openssl".into());
+                }
+                Ok(output)
+            },
+        )?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split("refs/tags/").nth(1))
+            .filter_map(|tag| Some((stable_release(tag)?, tag.to_string())))
+            .max()
+            .map(|(_, tag)| Self::Custom(tag))
+            .ok_or_else(|| "openssl/openssl has no stable release tags".into())
+    }
+
+    pub fn source_location(&self) -> LibraryLocation {
+        let (owner, repository, reference) = self.repository();
+        let location = GitLocation::github(owner, repository);
+        LibraryLocation::Git(match reference {
+            GitReference::Branch(branch) => location.branch(branch),
+            GitReference::Tag(tag) => location.tag(tag),
+            GitReference::Commit(commit) => location.commit(commit),
+        })
+    }
+
+    /// The same repository and reference as [`OpenSSLVersion::source_location`]
+    pub fn git_checkout(&self) -> GitCheckout {
+        let (owner, repository, reference) = self.repository();
+        GitCheckout::github(owner, repository).with_reference(reference)
+    }
+
+    fn repository(&self) -> (&'static str, &'static str, GitReference) {
+        match self {
+            Self::V1_1_1 => (
+                "syrel",
+                "openssl",
+                GitReference::Branch("OpenSSL_1_1_1-stable-Windows-pkgconfig".to_string()),
+            ),
+            Self::V3_0 => (
+                "openssl",
+                "openssl",
+                GitReference::Branch("openssl-3.0".to_string()),
+            ),
+            Self::V3_2 => (
+                "openssl",
+                "openssl",
+                GitReference::Branch("openssl-3.2".to_string()),
+            ),
+            Self::Custom(tag) => ("openssl", "openssl", GitReference::Tag(tag.clone())),
+        }
+    }
+
+    /// Returns the (major, minor) pair of the version, if it can be determined.
+    /// Custom tags are parsed from the upstream naming schemes `openssl-3.1.4` and `OpenSSL_1_1_1w`
+    pub fn major_minor(&self) -> Option<(u32, u32)> {
+        match self {
+            Self::V1_1_1 => Some((1, 1)),
+            Self::V3_0 => Some((3, 0)),
+            Self::V3_2 => Some((3, 2)),
+            Self::Custom(tag) => {
+                let lowercase = tag.to_lowercase();
+                let numbers = lowercase
+                    .trim_start_matches("openssl")
+                    .trim_start_matches(['-', '_']);
+                let mut parts = numbers
+                    .split(['.', '_'])
+                    .map(|part| part.trim_end_matches(|c: char| c.is_ascii_alphabetic()));
+                let major = parts.next()?.parse().ok()?;
+                let minor = parts.next()?.parse().ok()?;
+                Some((major, minor))
+            }
+        }
+    }
+
+    /// OpenSSL 3 changed the install layout (lib64 on some platforms) and introduced providers
+    pub fn is_v3(&self) -> bool {
+        self.major_minor()
+            .map(|(major, _)| major >= 3)
+            .unwrap_or(false)
+    }
+
+    /// QUIC support of libssl was added in OpenSSL 3.2
+    pub fn supports_quic(&self) -> bool {
+        self.major_minor()
+            .map(|version| version >= (3, 2))
+            .unwrap_or(false)
+    }
+
+    /// A short name that identifies the version, suitable for directory names
+    pub fn name(&self) -> String {
+        match self {
+            Self::V1_1_1 => "1.1.1".to_string(),
+            Self::V3_0 => "3.0".to_string(),
+            Self::V3_2 => "3.2".to_string(),
+            Self::Custom(tag) => tag.clone(),
+        }
+    }
+}
+
+/// The full version, such as `3.0.13` or `1.1.1w`, of the `openssl/opensslv.h` installed into the
+/// include directory. Branches like `openssl-3.0` only tell the version once they are built
+pub fn installed_version(include_directory: &Path) -> Option<String> {
+    let header =
+        std::fs::read_to_string(include_directory.join("openssl").join("opensslv.h")).ok()?;
+    header_version(&header)
+}
+
+/// `OPENSSL_VERSION_STR "3.0.13"` of OpenSSL 3, or
+/// `OPENSSL_VERSION_TEXT "OpenSSL 1.1.1w  11 Sep 2023"` of OpenSSL 1.1.1
+fn header_version(header: &str) -> Option<String> {
+    let quoted = |name: &str| {
+        header.lines().find_map(|line| {
+            let mut words = line
+                .trim_start_matches(['#', ' '])
+                .splitn(3, char::is_whitespace);
+            if words.next()? != "define" || words.next()? != name {
+                return None;
+            }
+            Some(words.next()?.trim().trim_matches('"').to_string())
+        })
+    };
+    if let Some(version) = quoted("OPENSSL_VERSION_STR") {
+        return Some(version);
+    }
+    quoted("OPENSSL_VERSION_TEXT")?
+        .split_whitespace()
+        .nth(1)
+        .map(|version| version.to_string())
+}
+
+/// The numbers of a stable release tag `openssl-X.Y.Z`
+fn stable_release(tag: &str) -> Option<(u32, u32, u32)> {
+    let mut numbers = tag.strip_prefix("openssl-")?.split('.');
+    let major = numbers.next()?.parse().ok()?;
+    let minor = numbers.next()?.parse().ok()?;
+    let patch = numbers.next()?.parse().ok()?;
+    if numbers.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn major_minor_of_custom_tags() {
+        let major_minor = |tag: &str| OpenSSLVersion::Custom(tag.to_string()).major_minor();
+        assert_eq!(major_minor("openssl-3.1.4"), Some((3, 1)));
+        assert_eq!(major_minor("OpenSSL_1_1_1w"), Some((1, 1)));
+        assert_eq!(major_minor("openssl-3.2.0-alpha1"), Some((3, 2)));
+        assert_eq!(major_minor("master"), None);
+        assert_eq!(OpenSSLVersion::V3_2.major_minor(), Some((3, 2)));
+    }
+
+    #[test]
+    fn v3_follows_the_version() {
+        assert!(!OpenSSLVersion::V1_1_1.is_v3());
+        assert!(OpenSSLVersion::Custom("openssl-3.0.13".to_string()).is_v3());
+    }
+
+    #[test]
+    fn stable_releases_only() {
+        assert_eq!(stable_release("openssl-3.3.1"), Some((3, 3, 1)));
+        assert_eq!(stable_release("openssl-3.4.0-alpha1"), None);
+        assert_eq!(stable_release("openssl-3.4.0-beta1"), None);
+        assert_eq!(stable_release("OpenSSL_1_1_1w"), None);
+        assert_eq!(stable_release("openssl-3.3"), None);
+    }
+
+    #[test]
+    fn version_of_installed_headers() {
+        assert_eq!(
+            header_version(
+                "# define OPENSSL_VERSION_MAJOR  3\n# define OPENSSL_VERSION_STR \"3.0.13\"\n"
+            ),
+            Some("3.0.13".to_string())
+        );
+        assert_eq!(
+            header_version("# define OPENSSL_VERSION_TEXT  \"OpenSSL 1.1.1w  11 Sep 2023\"\n"),
+            Some("1.1.1w".to_string())
+        );
+        assert_eq!(
+            header_version("#define OPENSSL_VERSION_NUMBER 0x30000000L\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn quic_follows_the_version() {
+        assert!(!OpenSSLVersion::V3_0.supports_quic());
+        assert!(OpenSSLVersion::Custom("openssl-3.3.1".to_string()).supports_quic());
+    }
+}
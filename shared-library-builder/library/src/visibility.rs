@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::OpenSSLBuildError;
+
+/// The file the exported symbols of Apple targets are listed in, in the build directory
+const EXPORTED_SYMBOLS_LIST_FILE: &str = "exported-symbols.txt";
+
+/// The version scripts OpenSSL 3 generates in the build directory and links the ELF libraries with
+const VERSION_SCRIPT_FILES: &[&str] = &["libcrypto.ld", "libssl.ld"];
+/// The version scripts of OpenSSL 1.1.1
+const VERSION_SCRIPT_FILES_V1: &[&str] = &["libcrypto.map", "libssl.map"];
+
+/// The makefile variable with the linker flags of the shared libraries only
+const LIBRARY_LDFLAGS_VARIABLE: &str = "LIB_LDFLAGS=";
+
+/// Restricts the symbols exported by the shared libraries, so that the shipped libssl and
+/// libcrypto do not clash with a system OpenSSL loaded into the same process, for example
+/// by a plugin host. ELF targets are linked with a version script whose version node
+/// replaces the `OPENSSL_x.y.z` ones, Apple targets with an exported symbols list.
+/// Patterns may use `*` wildcards, such as `EVP_*`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedSymbols {
+    patterns: Vec<String>,
+    version_node: String,
+}
+
+impl ExportedSymbols {
+    pub fn new(patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            patterns: patterns.into_iter().map(|pattern| pattern.into()).collect(),
+            version_node: "OPENSSL_BUNDLED".to_string(),
+        }
+    }
+
+    /// The symbol version of ELF targets, `OPENSSL_BUNDLED` by default
+    pub fn with_version_node(mut self, version_node: impl Into<String>) -> Self {
+        self.version_node = version_node.into();
+        self
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// A linker version script that exports the matching symbols and hides everything else
+    pub fn version_script(&self) -> String {
+        let mut script = format!("{} {{\n    global:\n", self.version_node);
+        for pattern in &self.patterns {
+            script.push_str(&format!("        {};\n", pattern));
+        }
+        script.push_str("    local: *;\n};\n");
+        script
+    }
+
+    /// An `ld64` exported symbols list, with the leading underscore of Mach-O symbols.
+    /// Both libraries are linked with the same list and ld64 fails on listed names a library
+    /// does not define, so names without wildcards are written as a wildcard that only matches
+    /// the name itself, `SSL_new` as `_SSL_ne[w]`
+    pub fn exported_symbols_list(&self) -> String {
+        self.patterns
+            .iter()
+            .map(|pattern| {
+                if pattern.contains(|character: char| matches!(character, '*' | '?' | '[')) {
+                    return format!("_{}\n", pattern);
+                }
+                let mut characters = pattern.chars();
+                match characters.next_back() {
+                    Some(last) => format!("_{}[{}]\n", characters.as_str(), last),
+                    None => String::new(),
+                }
+            })
+            .collect()
+    }
+
+    /// Replace the version scripts generated by OpenSSL in the build directory, `libcrypto.ld`
+    /// and `libssl.ld` of OpenSSL 3 or `libcrypto.map` and `libssl.map` of OpenSSL 1.1.1.
+    /// Run after Configure; make only regenerates them when they are older than the configuration
+    pub(crate) fn write_version_scripts(
+        &self,
+        build_directory: &Path,
+        is_v3: bool,
+    ) -> Result<(), OpenSSLBuildError> {
+        let script = self.version_script();
+        for file_name in version_script_files(is_v3) {
+            write_if_changed(&build_directory.join(file_name), &script)?;
+        }
+        Ok(())
+    }
+
+    /// Write the exported symbols list of Apple targets and add it to the linker flags of the
+    /// shared libraries in the generated makefile, so that the programs and the engines and
+    /// providers are linked without it. Run after Configure
+    pub(crate) fn write_exported_symbols_list(
+        &self,
+        build_directory: &Path,
+    ) -> Result<PathBuf, OpenSSLBuildError> {
+        let list = build_directory.join(EXPORTED_SYMBOLS_LIST_FILE);
+        write_if_changed(&list, &self.exported_symbols_list())?;
+
+        let makefile = build_directory.join("Makefile");
+        let flag = format!("-Wl,-exported_symbols_list,{}", list.display());
+        let contents = std::fs::read_to_string(&makefile)?;
+        let contents = with_library_ldflag(&contents, &flag).ok_or_else(|| {
+            OpenSSLBuildError::UnsupportedConfiguration(format!(
+                "{} does not set {}, the exported symbols can not be restricted",
+                makefile.display(),
+                LIBRARY_LDFLAGS_VARIABLE.trim_end_matches('=')
+            ))
+        })?;
+        write_if_changed(&makefile, &contents)?;
+        Ok(list)
+    }
+}
+
+fn version_script_files(is_v3: bool) -> &'static [&'static str] {
+    if is_v3 {
+        VERSION_SCRIPT_FILES
+    } else {
+        VERSION_SCRIPT_FILES_V1
+    }
+}
+
+/// The makefile with the flag appended to the linker flags of the shared libraries,
+/// unless it is there already. `None` when the makefile does not set them
+fn with_library_ldflag(makefile: &str, flag: &str) -> Option<String> {
+    let mut found = false;
+    let lines = makefile
+        .lines()
+        .map(|line| {
+            if !line.starts_with(LIBRARY_LDFLAGS_VARIABLE) {
+                return line.to_string();
+            }
+            found = true;
+            if line.split_whitespace().any(|existing| existing == flag) {
+                line.to_string()
+            } else {
+                format!("{} {}", line.trim_end(), flag)
+            }
+        })
+        .collect::<Vec<String>>();
+    if !found {
+        return None;
+    }
+    let mut contents = lines.join("\n");
+    if makefile.ends_with('\n') {
+        contents.push('\n');
+    }
+    Some(contents)
+}
+
+/// Unchanged files keep their timestamp, so that make does not relink the libraries
+fn write_if_changed(file: &Path, contents: &str) -> Result<(), OpenSSLBuildError> {
+    if std::fs::read_to_string(file).ok().as_deref() == Some(contents) {
+        return Ok(());
+    }
+    std::fs::write(file, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_script_exports_the_patterns() {
+        let exported_symbols =
+            ExportedSymbols::new(["SSL_*", "EVP_sha256"]).with_version_node("GT_OPENSSL");
+        assert_eq!(
+            exported_symbols.version_script(),
+            "GT_OPENSSL {\n    global:\n        SSL_*;\n        EVP_sha256;\n    local: *;\n};\n"
+        );
+    }
+
+    #[test]
+    fn exported_symbols_list_names_are_wildcards() {
+        let exported_symbols = ExportedSymbols::new(["SSL_*", "EVP_sha256", "X509_?"]);
+        assert_eq!(
+            exported_symbols.exported_symbols_list(),
+            "_SSL_*\n_EVP_sha25[6]\n_X509_?\n"
+        );
+    }
+
+    #[test]
+    fn version_script_files_of_the_version() {
+        assert_eq!(version_script_files(true), &["libcrypto.ld", "libssl.ld"]);
+        assert_eq!(
+            version_script_files(false),
+            &["libcrypto.map", "libssl.map"]
+        );
+    }
+
+    #[test]
+    fn library_ldflag_is_added_once() {
+        let makefile = "LDFLAGS=-Wl,-search_paths_first\nLIB_LDFLAGS=-dynamiclib\nBIN_LDFLAGS=\n";
+        let flag = "-Wl,-exported_symbols_list,/build/exported-symbols.txt";
+        let expected = "LDFLAGS=-Wl,-search_paths_first\nLIB_LDFLAGS=-dynamiclib -Wl,-exported_symbols_list,/build/exported-symbols.txt\nBIN_LDFLAGS=\n";
+        assert_eq!(
+            with_library_ldflag(makefile, flag).as_deref(),
+            Some(expected)
+        );
+        assert_eq!(
+            with_library_ldflag(expected, flag).as_deref(),
+            Some(expected)
+        );
+        assert_eq!(with_library_ldflag("LDFLAGS=\n", flag), None);
+    }
+}
@@ -0,0 +1,156 @@
+use shared_library_builder::{Library, LibraryCompilationContext};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::bindings::PUBLIC_HEADERS;
+use crate::openssl_library::OPENSSL_DIRECTORY_NAME;
+use crate::package::copy_directory;
+use crate::targets::architecture;
+use crate::universal::{lipo, merge_headers};
+use crate::{OpenSSLBuildError, OpenSSLLibrary};
+
+/// Builds the library for the Apple targets of the contexts and assembles `libcrypto.xcframework`
+/// and `libssl.xcframework` with a macOS slice, the only Apple platform the targets can express.
+/// Targets of the same platform are merged into a fat library. The headers and a module map
+/// of an `OpenSSL` module linking both libraries are part of `libcrypto.xcframework`,
+/// so that Xcode does not see the same headers twice
+pub fn compile_xcframeworks(
+    library: &OpenSSLLibrary,
+    contexts: &[LibraryCompilationContext],
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let first = contexts.first().ok_or("No targets to assemble")?;
+    let output = xcframework_directory(library, first);
+    let mut platforms: Vec<(&str, Vec<&LibraryCompilationContext>)> = vec![];
+    for context in contexts {
+        let target = context.target().to_string();
+        let platform = apple_platform(&target)
+            .ok_or_else(|| OpenSSLBuildError::UnsupportedTarget(target.clone()))?;
+        library.compile(context)?;
+        match platforms.iter_mut().find(|(name, _)| *name == platform) {
+            Some((_, contexts)) => contexts.push(context),
+            None => platforms.push((platform, vec![context])),
+        }
+    }
+
+    let mut crypto_slices = vec![];
+    let mut ssl_slices = vec![];
+    for (platform, contexts) in &platforms {
+        let slice = output.join("slices").join(platform);
+        if slice.exists() {
+            std::fs::remove_dir_all(&slice)?;
+        }
+        let (crypto, ssl): (Vec<PathBuf>, Vec<PathBuf>) = contexts
+            .iter()
+            .map(|context| library.compiled_libraries(context))
+            .unzip();
+        let headers = slice.join("Headers");
+        slice_headers(library, contexts, &headers)?;
+        std::fs::write(headers.join("module.modulemap"), module_map())?;
+        crypto_slices.push((fat_library(&crypto, &slice.join("crypto"))?, Some(headers)));
+        ssl_slices.push((fat_library(&ssl, &slice.join("ssl"))?, None));
+    }
+
+    Ok(vec![
+        create_xcframework(&crypto_slices, &output.join("libcrypto.xcframework"))?,
+        create_xcframework(&ssl_slices, &output.join("libssl.xcframework"))?,
+    ])
+}
+
+/// Where the xcframeworks are assembled
+pub fn xcframework_directory(
+    library: &OpenSSLLibrary,
+    context: &LibraryCompilationContext,
+) -> PathBuf {
+    context
+        .build_root()
+        .join(OPENSSL_DIRECTORY_NAME)
+        .join(library.version().name())
+        .join("xcframework")
+}
+
+/// The platform variant of an xcframework slice
+fn apple_platform(target: &str) -> Option<&'static str> {
+    if target.ends_with("-apple-darwin") {
+        Some("macos")
+    } else {
+        None
+    }
+}
+
+fn fat_library(libraries: &[PathBuf], destination: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    match libraries {
+        [library] => Ok(library.clone()),
+        _ => lipo(
+            &libraries
+                .iter()
+                .map(|library| library.as_path())
+                .collect::<Vec<&Path>>(),
+            destination,
+        ),
+    }
+}
+
+/// The headers of a slice. A fat x86_64 and arm64 slice gets headers that work for either
+fn slice_headers(
+    library: &OpenSSLLibrary,
+    contexts: &[&LibraryCompilationContext],
+    destination: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let include = |architecture_name: &str| {
+        contexts
+            .iter()
+            .find(|context| architecture(&context.target().to_string()) == architecture_name)
+            .map(|context| library.native_library_prefix(context).join("include"))
+    };
+    match (contexts, include("x86_64"), include("aarch64")) {
+        ([context], _, _) => copy_directory(
+            &library.native_library_prefix(context).join("include"),
+            destination,
+        ),
+        ([_, _], Some(x86_64), Some(arm64)) => merge_headers(&x86_64, &arm64, destination),
+        _ => Err(format!(
+            "Can not merge the headers of {}",
+            contexts
+                .iter()
+                .map(|context| context.target().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+        .into()),
+    }
+}
+
+fn module_map() -> String {
+    let headers = PUBLIC_HEADERS
+        .iter()
+        .map(|header| format!("    header \"{}\"\n", header))
+        .collect::<String>();
+    format!(
+        "module OpenSSL {{\n{}    link \"ssl\"\n    link \"crypto\"\n    export *\n}}\n",
+        headers
+    )
+}
+
+fn create_xcframework(
+    slices: &[(PathBuf, Option<PathBuf>)],
+    xcframework: &Path,
+) -> Result<PathBuf, Box<dyn Error>> {
+    // xcodebuild refuses to overwrite an existing xcframework
+    if xcframework.exists() {
+        std::fs::remove_dir_all(xcframework)?;
+    }
+    let mut command = Command::new("xcodebuild");
+    command.arg("-create-xcframework");
+    for (library, headers) in slices {
+        command.arg("-library").arg(library);
+        if let Some(headers) = headers {
+            command.arg("-headers").arg(headers);
+        }
+    }
+    let status = command.arg("-output").arg(xcframework).status()?;
+    if !status.success() {
+        return Err(format!("xcodebuild could not create {}", xcframework.display()).into());
+    }
+    Ok(xcframework.to_path_buf())
+}